@@ -0,0 +1,16 @@
+#version 330 core
+
+layout (location=0) in vec2 in_Position;
+layout (location=1) in vec4 in_Color;
+
+uniform vec2 player_pos;
+uniform vec2 tile_size;
+uniform float zoom;
+
+out vec4 outColor;
+
+void main()
+{
+    gl_Position = vec4((in_Position - player_pos) * tile_size / zoom, 0, 1);
+    outColor = in_Color;
+}
@@ -1,6 +1,7 @@
 #version 330 core
 
 in  vec2 outTexCoord;
+in  vec4 outColor;
 out vec4 fragColor;
 
 uniform sampler2D texture_sampler;
@@ -8,5 +9,5 @@ uniform sampler2D texture_sampler;
 void main()
 {
     // texture(texture_sampler, outTexCoord)
-    fragColor = texture(texture_sampler, outTexCoord);
+    fragColor = texture(texture_sampler, outTexCoord) * outColor;
 }
\ No newline at end of file
@@ -2,14 +2,17 @@
 
 layout (location=0) in vec3 in_Position;
 layout (location=1) in vec2 in_TextureCoord;
+layout (location=2) in vec4 in_Color;
 
 uniform vec2 player_pos;
 uniform float zoom;
 
 out vec2 outTexCoord;
+out vec4 outColor;
 
 void main()
 {
     gl_Position = vec4((in_Position.xy - player_pos) / zoom, in_Position.z, 1);
     outTexCoord = in_TextureCoord;
+    outColor = in_Color;
 }
\ No newline at end of file
@@ -0,0 +1,99 @@
+use crate::client::client_settings::ClientSettings;
+use crate::client::viewport::Viewport;
+use crate::misc::pos::{FloatPos, WorldPos};
+use crate::Player;
+
+/// Converts between window cursor coordinates and world positions.
+/// This is the same projection `tile-vertex.glsl` does, centered on the player.
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    pub pos: FloatPos,
+    pub zoom: f32,
+    width: f32,
+    height: f32,
+    gl_tile_width: f32,
+    gl_tile_height: f32,
+}
+
+impl Camera {
+    pub fn new(viewport: &Viewport, player: &Player, settings: &ClientSettings) -> Camera {
+        Self {
+            pos: FloatPos::new(player.pos_x, player.pos_y),
+            zoom: settings.zoom,
+            width: viewport.width as f32,
+            height: viewport.height as f32,
+            gl_tile_width: viewport.gl_tile_width,
+            gl_tile_height: viewport.gl_tile_height,
+        }
+    }
+
+    /// The size of a tile in OpenGL units before zooming.
+    pub fn get_gl_tile_size(&self) -> (f32, f32) {
+        (self.gl_tile_width, self.gl_tile_height)
+    }
+
+    /// The world position under the window coordinates, which start at the top left of the window.
+    pub fn screen_to_world(&self, x: f64, y: f64) -> FloatPos {
+        let gl_x = (x as f32 / self.width) * 2.0 - 1.0;
+        let gl_y = 1.0 - (y as f32 / self.height) * 2.0;
+        FloatPos::new(
+            self.pos.x + gl_x * self.zoom / self.gl_tile_width,
+            self.pos.y + gl_y * self.zoom / self.gl_tile_height,
+        )
+    }
+
+    /// The window coordinates of a world position. Positions that are off screen end up outside of the window size.
+    // Nothing is drawn at world positions in window coordinates yet, this is the inverse `screen_to_world` gets tested against.
+    #[allow(dead_code)]
+    pub fn world_to_screen(&self, pos: &FloatPos) -> (f64, f64) {
+        let gl_x = (pos.x - self.pos.x) * self.gl_tile_width / self.zoom;
+        let gl_y = (pos.y - self.pos.y) * self.gl_tile_height / self.zoom;
+        (
+            ((gl_x + 1.0) / 2.0 * self.width) as f64,
+            ((1.0 - gl_y) / 2.0 * self.height) as f64,
+        )
+    }
+
+    /// The tile under the window coordinates, if it is inside of the world.
+    pub fn get_tile_at(&self, x: f64, y: f64) -> Option<WorldPos> {
+        self.screen_to_world(x, y).get_tile_pos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::camera::Camera;
+    use crate::misc::pos::FloatPos;
+
+    fn create_camera(zoom: f32) -> Camera {
+        Camera {
+            pos: FloatPos::new(-120.5, 300.25),
+            zoom,
+            width: 1280.0,
+            height: 720.0,
+            gl_tile_width: 0.05,
+            gl_tile_height: 0.09,
+        }
+    }
+
+    #[test]
+    fn screen_and_world_positions_round_trip() {
+        for zoom in [0.5, 1.0, 3.0] {
+            let camera = create_camera(zoom);
+            for (x, y) in [(0.0, 0.0), (640.0, 360.0), (1280.0, 720.0), (17.0, 512.5), (-40.0, 900.0)] {
+                let (screen_x, screen_y) = camera.world_to_screen(&camera.screen_to_world(x, y));
+                assert!((screen_x - x).abs() < 0.01 && (screen_y - y).abs() < 0.01, "{} {} became {} {}", x, y, screen_x, screen_y);
+            }
+        }
+    }
+
+    #[test]
+    fn window_center_is_the_camera_position() {
+        let camera = create_camera(2.0);
+        assert_eq!(camera.screen_to_world(640.0, 360.0), camera.pos);
+        let (x, y) = camera.world_to_screen(&camera.pos.offset(0.0, 1.0));
+        // World y goes up while window y goes down.
+        assert_eq!(x, 640.0);
+        assert!(y < 360.0);
+    }
+}
@@ -3,6 +3,7 @@ use std::sync::mpsc::Receiver;
 
 use glfw::{Action, Key, Modifiers, MouseButton, WindowEvent};
 
+use crate::misc::pos::WorldPos;
 use crate::Player;
 
 pub struct ControlHandler {
@@ -87,6 +88,11 @@ impl ControlHandler {
 		}
 	}
 
+	/// The cursor position in window coordinates, starting at the top left of the window.
+	pub fn get_mouse_pos(&self) -> (f64, f64) {
+		(self.mouse_x, self.mouse_y)
+	}
+
 	pub fn acquire(&self, key: &EventKey) -> &EventType {
 		&self.events[key.id as usize].event_type
 	}
@@ -176,6 +182,7 @@ pub struct MovementAction {
 	down: EventKey,
 	left: EventKey,
 	right: EventKey,
+	fly: EventKey,
 }
 
 impl MovementAction {
@@ -201,38 +208,91 @@ impl MovementAction {
 				KeyMapping::key(Key::D),
 				"ui.right",
 			)),
+			fly: control_handler.register(Event::new(
+				EventType::new_toggle(false),
+				KeyMapping::key(Key::F),
+				"ui.fly",
+			)),
 		}
 	}
 
+	/// Hands the pressed keys to the player, which turns them into movement on its next tick.
 	pub fn event_apply(&self, control_handler: &ControlHandler, player: &mut Player) {
-		let mut vel_x = 0f32;
-		let mut vel_y = 0f32;
+		let mut input_x = 0f32;
+		let mut input_y = 0f32;
 
 		if let EventType::Persistent { pressed } = control_handler.acquire(&self.up) {
 			if *pressed {
-				vel_y += 1f32;
+				input_y += 1f32;
 			}
 		}
 
 		if let EventType::Persistent { pressed } = control_handler.acquire(&self.down) {
 			if *pressed {
-				vel_y -= 1f32;
+				input_y -= 1f32;
 			}
 		}
 
 		if let EventType::Persistent { pressed } = control_handler.acquire(&self.left) {
 			if *pressed {
-				vel_x -= 1f32;
+				input_x -= 1f32;
 			}
 		}
 
 		if let EventType::Persistent { pressed } = control_handler.acquire(&self.right) {
 			if *pressed {
-				vel_x += 1f32;
+				input_x += 1f32;
 			}
 		}
 
-		player.vel_x = vel_x;
-		player.vel_y = vel_y;
+		if let EventType::Toggle { state } = control_handler.acquire(&self.fly) {
+			player.flying = *state;
+		}
+
+		player.input_x = input_x;
+		player.input_y = input_y;
+		player.jump_held = input_y > 0f32;
 	}
-}
\ No newline at end of file
+}
+
+/// Mining and placing at the tile under the cursor, and picking the hotbar slot to use.
+pub struct InteractionAction {
+	mine: EventKey,
+	place: EventKey,
+	hotbar: Vec<EventKey>,
+}
+
+impl InteractionAction {
+	pub fn new(control_handler: &mut ControlHandler) -> InteractionAction {
+		InteractionAction {
+			mine: control_handler.register(Event::new(
+				EventType::new_persistent(),
+				KeyMapping::new_mouse(MouseButton::Button1, Modifiers::empty()),
+				"ui.mine",
+			)),
+			place: control_handler.register(Event::new(
+				EventType::new_persistent(),
+				KeyMapping::new_mouse(MouseButton::Button2, Modifiers::empty()),
+				"ui.place",
+			)),
+			hotbar: [Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9, Key::Num0]
+				.iter()
+				.map(|key| control_handler.register(Event::new(EventType::new_request(), KeyMapping::key(*key), "ui.hotbar")))
+				.collect(),
+		}
+	}
+
+	/// Points the player at `target`. The world mines or places there on its next tick.
+	pub fn event_apply(&self, control_handler: &ControlHandler, player: &mut Player, target: Option<WorldPos>) {
+		player.target = target;
+		player.mining = matches!(control_handler.acquire(&self.mine), EventType::Persistent { pressed: true });
+		player.placing = matches!(control_handler.acquire(&self.place), EventType::Persistent { pressed: true });
+		for (slot, key) in self.hotbar.iter().enumerate() {
+			if let EventType::Request { requests } = control_handler.acquire(key) {
+				if *requests > 0 {
+					player.select_slot(slot);
+				}
+			}
+		}
+	}
+}
@@ -1,12 +1,15 @@
 use glfw::{Context, Glfw, OpenGlProfileHint, Window, WindowHint};
 
+use crate::client::camera::Camera;
 use crate::client::client_settings::ClientSettings;
-use crate::client::controller::{ControlHandler, MovementAction};
+use crate::client::controller::{ControlHandler, InteractionAction, MovementAction};
 use crate::client::fps::FpsCounter;
 use crate::client::opengl::gl;
 use crate::client::opengl::gl::{COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT};
+use crate::client::render::overlay_renderer::OverlayRenderer;
 use crate::client::render::world_renderer::WorldRenderer;
 use crate::client::viewport::Viewport;
+use crate::misc::pos::WorldPos;
 use crate::misc::random_quote;
 use crate::Player;
 use crate::world::{PlayerId, World};
@@ -17,6 +20,7 @@ mod fps;
 mod controller;
 mod client_settings;
 mod render;
+mod camera;
 
 pub struct ClientHandler {
     glfw: Glfw,
@@ -28,9 +32,12 @@ pub struct ClientHandler {
     player_id: PlayerId,
     world: Option<World>,
     world_renderer: WorldRenderer,
+    overlay_renderer: OverlayRenderer,
+    hovered_tile: Option<WorldPos>,
 
     control_handler: ControlHandler,
     movement_action: MovementAction,
+    interaction_action: InteractionAction,
 }
 
 impl ClientHandler  {
@@ -40,12 +47,28 @@ impl ClientHandler  {
         match &mut self.world {
             None => {}
             Some(world) => {
-                for x in world.chunk_updates.drain() {
-                    self.world_renderer.rebuild_chunk(&x);
-                }
+                self.world_renderer.apply_world_events();
 
                 let player = world.acquire_player(&self.player_id);
                 self.world_renderer.draw(&self.viewport, player, &self.settings);
+
+                // The player moved since the last input tick, so the tile under the cursor might have changed.
+                let camera = Camera::new(&self.viewport, player, &self.settings);
+                let (mouse_x, mouse_y) = self.control_handler.get_mouse_pos();
+                self.hovered_tile = camera.get_tile_at(mouse_x, mouse_y);
+                for falling in world.get_falling_tiles() {
+                    self.overlay_renderer.add_falling_tile(falling, world.get_paints());
+                }
+                for drop in world.get_drops() {
+                    self.overlay_renderer.add_drop(drop);
+                }
+                for (pos, damage) in world.get_damaged_tiles() {
+                    self.overlay_renderer.add_crack(pos, damage.get_progress());
+                }
+                if let Some(pos) = &self.hovered_tile {
+                    self.overlay_renderer.add_highlight(pos);
+                }
+                self.overlay_renderer.draw(&camera);
             }
         };
         self.window.swap_buffers();
@@ -59,16 +82,41 @@ impl ClientHandler  {
         match &mut self.world {
             None => {}
             Some(world) => {
-                world.tick();
+                if let Err(error) = world.tick() {
+                    println!("{}", error);
+                }
                 self.world_renderer.tick(world, &self.viewport, world.acquire_player(&self.player_id), &self.settings);
 
             }
         };
     }
 
+    /// The tile under the cursor as of the last frame.
+    pub fn get_hovered_tile(&self) -> Option<&WorldPos> {
+        self.hovered_tile.as_ref()
+    }
+
+    /// If the window got asked to close.
+    pub fn should_close(&self) -> bool {
+        self.window.should_close()
+    }
+
+    /// Takes the world away from the client, to save it for example.
+    pub fn leave_world(&mut self) -> Option<World> {
+        self.hovered_tile = None;
+        self.world.take()
+    }
+
     pub fn join_world(&mut self, mut world: World) {
-        let player = Player::new();
+        let player = match world.load_player() {
+            Ok(player) => player.unwrap_or_else(Player::new),
+            Err(error) => {
+                println!("Could not load the player. {}", error);
+                Player::new()
+            }
+        };
         self.player_id = world.player_join(player);
+        self.world_renderer.subscribe(&mut world);
         self.world = Some(world);
     }
 
@@ -85,7 +133,9 @@ impl ClientHandler  {
         match &mut self.world {
             None => {}
             Some(world) => {
-                self.movement_action.event_apply(&self.control_handler, world.acquire_player_mut(&self.player_id));
+                let player = world.acquire_player_mut(&self.player_id);
+                self.movement_action.event_apply(&self.control_handler, player);
+                self.interaction_action.event_apply(&self.control_handler, player, self.hovered_tile);
             }
         };
 
@@ -127,7 +177,9 @@ impl ClientHandler  {
         let settings = ClientSettings::new();
         let mut control_handler = controller::ControlHandler::new(events);
         let movement_action = MovementAction::new(&mut control_handler);
+        let interaction_action = InteractionAction::new(&mut control_handler);
         let world_renderer = WorldRenderer::new(&mut control_handler);
+        let overlay_renderer = OverlayRenderer::new();
 
 
         //glfw.set_swap_interval(SwapInterval::Sync(1));
@@ -156,8 +208,11 @@ impl ClientHandler  {
             world: None,
             player_id: PlayerId::default(),
             world_renderer,
+            overlay_renderer,
+            hovered_tile: None,
             control_handler,
             movement_action,
+            interaction_action,
         }
     }
 }
\ No newline at end of file
@@ -34,6 +34,8 @@ pub struct AtlasSettings {
 pub enum ImageId {
     Tile(TileId),
     Wall(WallId),
+    /// The sheet with every multi tile object.
+    Object,
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -59,6 +61,7 @@ impl Image {
         let image_type = match possible_type {
             "tile" => ImageId::Tile(TileId { id }),
             "wall" => ImageId::Wall(WallId { id }),
+            "object" => ImageId::Object,
             &_ => panic!("Could not identify image type called {}", possible_type)
         };
 
@@ -67,6 +70,12 @@ impl Image {
             image,
         }
     }
+
+    /// A flat gray image that stands in for a sprite sheet that is missing.
+    pub fn placeholder(id: ImageId, width: u32, height: u32) -> Image {
+        let image = image::RgbaImage::from_pixel(width, height, image::Rgba([160, 160, 160, 255]));
+        Self::new(DynamicImage::ImageRgba8(image), id)
+    }
 }
 
 pub struct Atlas {
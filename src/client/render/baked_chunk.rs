@@ -1,4 +1,4 @@
-use glam::{Vec2, Vec3};
+use glam::{Vec2, Vec3, Vec4};
 use std::ops::{Add, Sub};
 use crate::client::render::world_renderer::NeighborImageLocation;
 use crate::client::opengl::builder::{QuadBuilder, QuadDepthBuilder};
@@ -10,7 +10,9 @@ use crate::client::viewport::Viewport;
 use crate::misc::pos::ChunkPos;
 use crate::misc::util::CHUNK_SIZE;
 use crate::World;
-use crate::world::tile::Tile;
+use crate::world::object::ObjectType;
+use crate::world::paint::PaintPalette;
+use crate::world::tile::{Tile, TileShape};
 use crate::world::{Grid, tile, wall};
 use crate::world::wall::Wall;
 
@@ -53,7 +55,7 @@ impl BakedChunk {
 					let i = Self::get_variant(pos, y, x);
 					if tile_x.id != tile::AIR {
 						vertices += 6; // quad
-						builder.add_tile(x, y, tile_x, tile_atlas, i);
+						builder.add_tile(x, y, tile_x, tile_atlas, world.get_paints(), i);
 					}
 				}
 			}
@@ -93,6 +95,7 @@ impl BakedChunk {
 pub struct ChunkVertexBuilder {
 	pos: Vec<Vec3>,
 	textures: Vec<Vec2>,
+	colors: Vec<Vec4>,
 	gl_chunk_x: f32,
 	gl_chunk_y: f32,
 	gl_tile_width: f32,
@@ -107,25 +110,28 @@ impl ChunkVertexBuilder {
 			gl_chunk_y: (chunk_pos.y as f32) * (viewport.gl_tile_height * CHUNK_SIZE as f32),
 			gl_tile_width: viewport.gl_tile_width,
 			gl_tile_height: viewport.gl_tile_height,
-			textures: Vec::new()
+			textures: Vec::new(),
+			colors: Vec::new(),
 		}
 	}
 
-	pub fn add_tile(&mut self, x: usize, y: usize, tile: &Tile, atlas: &Atlas, var: u64) {
+	pub fn add_tile(&mut self, x: usize, y: usize, tile: &Tile, atlas: &Atlas, paints: &PaintPalette, var: u64) {
+		// Half blocks only fill the lower half of the tile.
+		let height = if tile.shape == TileShape::Half { 0.5f32 } else { 1f32 };
 		let gl_pos = Vec2::new(
 			self.gl_chunk_x + (x as f32 * self.gl_tile_width),
-			self.gl_chunk_y + ((y as f32 + 1f32) * self.gl_tile_height),
+			self.gl_chunk_y + ((y as f32 + height) * self.gl_tile_height),
 		);
 
-		// Tile type position of the sprite.
-		let (type_x, type_y) = NeighborImageLocation::from(tile).get_tile_pos();
-		let image = atlas.get_image(ImageId::Tile(tile.get_id().clone()));
-		let image_pos = Vec2::new(image.x, image.y);
-
+		// Tile type position of the sprite. Multi tile objects pick their part from the object sheet instead of connecting to neighbors.
 		// We have 3 variants. An entry of tiles is 5x4 but are stacked horizontally for every variant.
-		let variant_offset = (var % 3u64) as f32 * 4f32;
+		let (image, (type_x, type_y), variant_offset) = match ObjectType::get(tile.get_id()) {
+			Some(object) => (atlas.get_image(ImageId::Object), object.get_sprite_pos(tile.frame_x, tile.frame_y), 0f32),
+			None => (atlas.get_image(ImageId::Tile(*tile.get_id())), NeighborImageLocation::from(tile).get_tile_pos(), (var % 3u64) as f32 * 4f32),
+		};
+		let image_pos = Vec2::new(image.x, image.y);
 
-		// Out layout is 12 x 5.
+		// Out layout is 12 x 5, the object sheet has the same size.
 		// A single tile is always (image.width / 12f32, image.height / 5f32) of size.
 		let item_tile_size = Vec2::new(
 			image.width / 12f32,
@@ -145,14 +151,44 @@ impl ChunkVertexBuilder {
 		);
 
 		// Add stuff
-		self.pos.add_quad(gl_pos, Vec2::new(self.gl_tile_width, -self.gl_tile_height), 1f32);
-		self.textures.add_quad(image_pos.add(image_offset), item_tile_size);
+		let start = self.pos.len();
+		self.pos.add_quad(gl_pos, Vec2::new(self.gl_tile_width, -self.gl_tile_height * height), 1f32);
+		self.textures.add_quad(image_pos.add(image_offset), Vec2::new(item_tile_size.x, item_tile_size.y * height));
+		self.collapse_slope(start, tile.shape);
+
+		// Paint tints the whole quad.
+		let (r, g, b) = paints.get_color(&tile.paint);
+		for _ in 0..6 {
+			self.colors.push(Vec4::new(r, g, b, 1f32));
+		}
+	}
+
+	/// Turns the last quad into a triangle by moving the corner that is not solid onto another corner.
+	fn collapse_slope(&mut self, start: usize, shape: TileShape) {
+		// A quad is (BottomLeft, TopLeft, BottomRight) (BottomRight, TopLeft, TopRight).
+		let (target, sources): (usize, &[usize]) = match shape {
+			TileShape::Full | TileShape::Half => return,
+			// TopLeft onto BottomLeft
+			TileShape::SlopeBottomRight => (0, &[1, 4]),
+			// TopRight onto BottomRight
+			TileShape::SlopeBottomLeft => (2, &[5]),
+			// BottomLeft onto TopLeft
+			TileShape::SlopeTopRight => (1, &[0]),
+			// BottomRight onto BottomLeft
+			TileShape::SlopeTopLeft => (0, &[2, 3]),
+		};
+
+		for source in sources {
+			self.pos[start + source] = self.pos[start + target];
+			self.textures[start + source] = self.textures[start + target];
+		}
 	}
 
 	pub fn export(self, program: &Program) -> VertexData {
-		let mut layout = VertexData::new(2);
+		let mut layout = VertexData::new(3);
 		layout.add_vertex_array(&program.get_attribute("in_Position"), self.pos, BufferUsage::StaticDraw, VertexDivisor::Vertex);
 		layout.add_vertex_array(&program.get_attribute("in_TextureCoord"), self.textures, BufferUsage::StaticDraw, VertexDivisor::Vertex);
+		layout.add_vertex_array(&program.get_attribute("in_Color"), self.colors, BufferUsage::StaticDraw, VertexDivisor::Vertex);
 		layout
 	}
 }
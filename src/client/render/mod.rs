@@ -1,2 +1,3 @@
 mod baked_chunk;
+pub(crate) mod overlay_renderer;
 pub(crate) mod world_renderer;
\ No newline at end of file
@@ -0,0 +1,127 @@
+use glam::{Vec2, Vec4};
+
+use crate::client::camera::Camera;
+use crate::client::opengl::builder::QuadBuilder;
+use crate::client::opengl::gl;
+use crate::client::opengl::gl::{BufferUsage, VertexDivisor};
+use crate::client::opengl::sgl::{Program, Uniform, UniformType, VertexData};
+use crate::misc::pos::WorldPos;
+use crate::world::falling::FallingTile;
+use crate::world::paint::PaintPalette;
+use crate::world::support::ItemDrop;
+use crate::world::tile;
+use crate::read_asset_string;
+
+/// Thickness of the hovered tile outline in tiles.
+const OUTLINE_WIDTH: f32 = 0.0625;
+/// The cracks of a damaged tile as (x, y, width, height) inside of the tile. More of them show up the closer the tile is to breaking.
+const CRACKS: [(f32, f32, f32, f32); 4] = [
+    (0.45, 0.3, 0.08, 0.45),
+    (0.15, 0.6, 0.35, 0.08),
+    (0.55, 0.25, 0.3, 0.08),
+    (0.2, 0.1, 0.08, 0.35),
+];
+
+/// Draws flat colored shapes on top of the world, like the outline of the hovered tile.
+/// Unlike the baked chunks, overlay positions are in tiles and are rebuilt every frame.
+pub struct OverlayRenderer {
+    program: Program,
+
+    player_pos: Uniform<(f32, f32)>,
+    tile_size: Uniform<(f32, f32)>,
+    gl_zoom: Uniform<f32>,
+
+    pos: Vec<Vec2>,
+    colors: Vec<Vec4>,
+}
+
+impl OverlayRenderer {
+    pub fn new() -> OverlayRenderer {
+        let program = Program::create(
+            read_asset_string("shader/overlay-vertex.glsl"),
+            read_asset_string("shader/overlay-fragment.glsl"),
+            None,
+        );
+
+        Self {
+            player_pos: program.get_uniform("player_pos"),
+            tile_size: program.get_uniform("tile_size"),
+            gl_zoom: program.get_uniform("zoom"),
+            program,
+            pos: Vec::new(),
+            colors: Vec::new(),
+        }
+    }
+
+    /// Adds a rectangle with its bottom left corner at `x`, `y`.
+    pub fn add_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Vec4) {
+        self.pos.add_quad(Vec2::new(x, y), Vec2::new(width, height));
+        for _ in 0..6 {
+            self.colors.push(color);
+        }
+    }
+
+    /// Fills the tile lightly and draws a border around it.
+    pub fn add_highlight(&mut self, pos: &WorldPos) {
+        let (x, y) = (pos.get_x() as f32, pos.get_y() as f32);
+        let fill = Vec4::new(1f32, 1f32, 1f32, 0.15f32);
+        let border = Vec4::new(1f32, 1f32, 1f32, 0.6f32);
+
+        self.add_rect(x, y, 1f32, 1f32, fill);
+        self.add_rect(x, y, 1f32, OUTLINE_WIDTH, border);
+        self.add_rect(x, y + 1f32 - OUTLINE_WIDTH, 1f32, OUTLINE_WIDTH, border);
+        self.add_rect(x, y + OUTLINE_WIDTH, OUTLINE_WIDTH, 1f32 - OUTLINE_WIDTH * 2f32, border);
+        self.add_rect(x + 1f32 - OUTLINE_WIDTH, y + OUTLINE_WIDTH, OUTLINE_WIDTH, 1f32 - OUTLINE_WIDTH * 2f32, border);
+    }
+
+    /// Darkens the tile and draws cracks over it depending on how close it is to breaking.
+    pub fn add_crack(&mut self, pos: &WorldPos, progress: f32) {
+        let (x, y) = (pos.get_x() as f32, pos.get_y() as f32);
+        let stages = ((progress * CRACKS.len() as f32).ceil() as usize).min(CRACKS.len());
+        self.add_rect(x, y, 1f32, 1f32, Vec4::new(0f32, 0f32, 0f32, progress * 0.3f32));
+        for (crack_x, crack_y, width, height) in &CRACKS[..stages] {
+            self.add_rect(x + crack_x, y + crack_y, *width, *height, Vec4::new(0f32, 0f32, 0f32, 0.7f32));
+        }
+    }
+
+    /// Falling tiles are not part of any chunk, so they get drawn as a block in the color of their tile and paint.
+    pub fn add_falling_tile(&mut self, falling: &FallingTile, paints: &PaintPalette) {
+        let (r, g, b) = match falling.tile.id {
+            tile::SAND => (0.73, 0.66, 0.33),
+            tile::SILT_BLOCK => (0.42, 0.42, 0.46),
+            tile::EBONSAND_BLOCK => (0.4, 0.38, 0.48),
+            tile::PEARLSAND_BLOCK => (0.93, 0.88, 0.85),
+            _ => (0.5, 0.5, 0.5),
+        };
+        let (paint_r, paint_g, paint_b) = paints.get_color(&falling.tile.paint);
+        self.add_rect(falling.x as f32, falling.y, 1f32, 1f32, Vec4::new(r * paint_r, g * paint_g, b * paint_b, 1f32));
+    }
+
+    /// Drops are drawn as a small box lying on the bottom of their tile until the player picks them up.
+    pub fn add_drop(&mut self, drop: &ItemDrop) {
+        let (x, y) = (drop.pos.get_x() as f32, drop.pos.get_y() as f32);
+        self.add_rect(x + 0.3f32, y, 0.4f32, 0.4f32, Vec4::new(0.9f32, 0.8f32, 0.3f32, 1f32));
+    }
+
+    /// Draws everything added since the last draw and clears it.
+    pub fn draw(&mut self, camera: &Camera) {
+        if self.pos.is_empty() {
+            return;
+        }
+
+        let vertices = self.pos.len() as i32;
+        let mut layout = VertexData::new(2);
+        layout.add_vertex_array(&self.program.get_attribute("in_Position"), std::mem::take(&mut self.pos), BufferUsage::StreamDraw, VertexDivisor::Vertex);
+        layout.add_vertex_array(&self.program.get_attribute("in_Color"), std::mem::take(&mut self.colors), BufferUsage::StreamDraw, VertexDivisor::Vertex);
+
+        self.program.bind();
+        self.player_pos.apply((camera.pos.x, camera.pos.y));
+        self.tile_size.apply(camera.get_gl_tile_size());
+        self.gl_zoom.apply(camera.zoom);
+
+        layout.bind();
+        gl::draw_arrays(gl::TRIANGLES, 0, vertices);
+        layout.unbind();
+        self.program.unbind();
+    }
+}
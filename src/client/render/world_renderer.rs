@@ -1,22 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::read_dir;
 use std::path::Path;
 
+use crossbeam::channel::Receiver;
 use glfw::Key;
 
 use crate::{Player, read_asset_string};
 use crate::client::client_settings::ClientSettings;
 use crate::client::controller::{ControlHandler, Event, EventKey, EventType, KeyMapping};
 use crate::client::opengl::gl;
-use crate::client::opengl::hlgl::{Atlas, AtlasSettings, Image, Sampler2d};
+use crate::client::opengl::hlgl::{Atlas, AtlasSettings, Image, ImageId, Sampler2d};
 use crate::client::opengl::sgl::{Program, Uniform, UniformType};
 use crate::client::render::baked_chunk::BakedChunk;
 use crate::client::viewport::Viewport;
 use crate::misc::pos::{ChunkPos, WorldPos};
 use crate::misc::util::{CHUNK_SIZE, Direction};
 use crate::world::neighbor::NeighborAware;
+use crate::world::object::{OBJECT_SHEET_HEIGHT, OBJECT_SHEET_WIDTH};
+use crate::world::event::{EventKind, WorldEvent};
+use crate::world::tile::Tile;
 use crate::world::World;
 
+/// The size of a single tile on the object sheet in pixels.
+const OBJECT_SPRITE_SIZE: u32 = 16;
+
 pub struct WorldRenderer {
     program: Program,
 
@@ -26,6 +33,9 @@ pub struct WorldRenderer {
     atlas: Atlas,
 
     baked_chunks: HashMap<ChunkPos, BakedChunk>,
+    /// Baked chunks that are outdated. They keep getting drawn until the next tick rebakes them.
+    dirty_chunks: HashSet<ChunkPos>,
+    world_events: Option<Receiver<WorldEvent>>,
 
     debug_mode: bool,
     cull_chunks: bool,
@@ -61,6 +71,15 @@ impl WorldRenderer {
             images.push(image);
         }
 
+        let object_sheet = Path::new("./assets/sprite/object-0.png");
+        if object_sheet.exists() {
+            images.push(Image::load(object_sheet));
+        } else {
+            println!("Could not find the object sprites at {}, objects get drawn as gray boxes.", object_sheet.display());
+            let (width, height) = (OBJECT_SHEET_WIDTH as u32 * OBJECT_SPRITE_SIZE, OBJECT_SHEET_HEIGHT as u32 * OBJECT_SPRITE_SIZE);
+            images.push(Image::placeholder(ImageId::Object, width, height));
+        }
+
         let atlas = Atlas::new(images, AtlasSettings {
             mipmaps: 3
         });
@@ -73,6 +92,8 @@ impl WorldRenderer {
             atlas,
             program,
             baked_chunks: HashMap::new(),
+            dirty_chunks: HashSet::new(),
+            world_events: None,
             debug_mode: false,
             cull_chunks: true,
             debug_mode_key: control_handler.register(Event::new(EventType::new_toggle(false), KeyMapping::key(Key::F1), "debug.mode")),
@@ -81,6 +102,40 @@ impl WorldRenderer {
         }
     }
 
+    pub fn subscribe(&mut self, world: &mut World) {
+        self.world_events = Some(world.subscribe(&EventKind::all()));
+        self.rebuild_all();
+    }
+
+    /// Marks the baked chunks that changed since the last frame as dirty.
+    pub fn apply_world_events(&mut self) {
+        let events: Vec<WorldEvent> = match &self.world_events {
+            Some(receiver) => receiver.try_iter().collect(),
+            None => return,
+        };
+
+        for event in events {
+            match event {
+                WorldEvent::TileChanged { pos, old, new } => self.tile_change(&pos, &old, &new),
+                // Walls and liquids do not get baked yet.
+                WorldEvent::WallChanged { .. } | WorldEvent::LiquidChanged { .. } => {}
+                // Neighbors connect to the new chunk on their borders.
+                WorldEvent::ChunkLoaded(pos) => {
+                    for dir in Direction::iter() {
+                        if let Some(neighbor) = pos.shift(dir) { self.rebuild_chunk(&neighbor); }
+                    }
+                }
+                WorldEvent::ChunkUnloaded(pos) => {
+                    self.baked_chunks.remove(&pos);
+                    self.dirty_chunks.remove(&pos);
+                }
+                // Anything in the chunk might have changed.
+                WorldEvent::ChunkEdited(pos) => self.rebuild_chunk(&pos),
+                WorldEvent::Lagged => self.rebuild_all(),
+            }
+        }
+    }
+
     pub fn event_apply(&mut self, control_handler: &ControlHandler) {
         if let EventType::Toggle { state } = control_handler.acquire(&self.debug_mode_key) {
             self.debug_mode = *state;
@@ -105,6 +160,13 @@ impl WorldRenderer {
         player: &Player,
         settings: &ClientSettings,
     ) {
+        for pos in std::mem::take(&mut self.dirty_chunks) {
+            match BakedChunk::new(&self.program, viewport, world, &pos, &self.atlas) {
+                Some(baked_chunk) => self.baked_chunks.insert(pos, baked_chunk),
+                None => self.baked_chunks.remove(&pos),
+            };
+        }
+
         let player_pos = ChunkPos::from_player(player);
         let render_distance = settings.render_distance as i32;
 
@@ -128,14 +190,22 @@ impl WorldRenderer {
         }
     }
 
-    pub fn tile_change(&mut self, pos: &WorldPos) {
-        let chunk_tile_pos = pos.get_chunk_sub_pos();
-        for dir in Direction::iter() {
-            if chunk_tile_pos.is_border(dir) {
-                if let Some(pos) = pos.get_chunk_pos().shift(dir) { self.baked_chunks.remove(&pos); }
+    /// Only the chunk of the tile needs rebaking, unless the tile sits on a border and its neighbors in the next chunk
+    /// connect to it differently now.
+    pub fn tile_change(&mut self, pos: &WorldPos, old: &Tile, new: &Tile) {
+        if old == new {
+            return;
+        }
+
+        self.rebuild_chunk(pos.get_chunk_pos());
+        if old.id != new.id || old.shape != new.shape {
+            let chunk_tile_pos = pos.get_chunk_sub_pos();
+            for dir in Direction::iter() {
+                if chunk_tile_pos.is_border(dir) {
+                    if let Some(pos) = pos.get_chunk_pos().shift(dir) { self.rebuild_chunk(&pos); }
+                }
             }
         }
-        self.baked_chunks.remove(pos.get_chunk_pos());
     }
 
     pub fn draw(&self, viewport: &Viewport, player: &Player, settings: &ClientSettings) {
@@ -168,10 +238,14 @@ impl WorldRenderer {
 
     pub fn rebuild_all(&mut self) {
         self.baked_chunks.clear();
+        self.dirty_chunks.clear();
     }
 
+    /// Rebakes the chunk on the next tick if it is baked.
     pub fn rebuild_chunk(&mut self, chunk_pos: &ChunkPos) {
-        self.baked_chunks.remove(chunk_pos);
+        if self.baked_chunks.contains_key(chunk_pos) {
+            self.dirty_chunks.insert(*chunk_pos);
+        }
     }
 }
 
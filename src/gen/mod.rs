@@ -5,16 +5,21 @@ use crossbeam::channel::{Receiver, Sender, unbounded};
 use rayon::{ThreadPool, ThreadPoolBuilder};
 
 use crate::gen::noise::{NoiseGenerator, TERRAIN};
-use crate::misc::pos::{ChunkPos, ChunkSubPos};
-use crate::misc::util::{CHUNK_SIZE, Direction};
+use crate::misc::pos::{ChunkPos, ChunkSubPos, WorldPos};
+use crate::misc::util::CHUNK_SIZE;
 use crate::world::{Chunk, Grid, tile};
+use crate::world::liquid::Liquid;
 use crate::world::neighbor::{NeighborAware, NeighborMatrix};
+use crate::world::schematic::Schematic;
 use crate::world::tile::Tile;
 use crate::world::wall::Wall;
 
 mod gen_const;
 mod noise;
 
+/// Chunks get moved around by value while generating, which needs more than the default stack in debug builds.
+const GENERATOR_STACK_SIZE: usize = 8 * 1024 * 1024;
+
 #[derive(Copy, Clone)]
 pub struct GenerationContext {
     noise: NoiseGenerator,
@@ -32,6 +37,14 @@ pub struct WorldGenerator {
     thread_pool: ThreadPool,
 
     queue_chunks: HashSet<ChunkPos>,
+    structures: Arc<Vec<Structure>>,
+}
+
+/// A schematic that gets placed into every chunk it overlaps while generating.
+#[derive(Clone)]
+pub struct Structure {
+    pub schematic: Arc<Schematic>,
+    pub origin: WorldPos,
 }
 
 impl WorldGenerator {
@@ -49,8 +62,9 @@ impl WorldGenerator {
             }),
             sender,
             receiver,
-            thread_pool: ThreadPoolBuilder::new().build().unwrap(),
+            thread_pool: ThreadPoolBuilder::new().stack_size(GENERATOR_STACK_SIZE).build().unwrap(),
             queue_chunks: HashSet::new(),
+            structures: Arc::new(Vec::new()),
         }
     }
 
@@ -58,15 +72,22 @@ impl WorldGenerator {
         if !self.queue_chunks.contains(pos) {
             let dup_pos = pos.clone();
             let context = self.context.clone();
+            let structures = self.structures.clone();
             let sender = self.sender.clone();
             self.thread_pool.spawn(move || {
-                sender.send((dup_pos, Self::gen_chunk(&context, &dup_pos))).unwrap();
+                sender.send((dup_pos, Self::gen_chunk(&context, &structures, &dup_pos))).unwrap();
             });
 
             self.queue_chunks.insert(*pos);
         }
     }
 
+    /// If the chunk is being generated right now.
+    pub fn is_queued(&self, pos: &ChunkPos) -> bool {
+        self.queue_chunks.contains(pos)
+    }
+
+    /// Takes the chunks that finished generating. They can be queued again once they got unloaded.
     pub fn generate_chunks(&mut self) -> Option<Vec<(ChunkPos, Chunk)>> {
         let result = self.receiver.try_recv();
         if result.is_err() {
@@ -79,15 +100,27 @@ impl WorldGenerator {
                 out.push((pos, chunk));
             }
 
+            for (pos, _) in &out {
+                self.queue_chunks.remove(pos);
+            }
             Some(out)
         }
     }
 
-    fn gen_chunk(context: &Arc<GenerationContext>, pos: &ChunkPos) -> Chunk {
+    /// Places `schematic` with its bottom left corner at `origin` into every chunk generated from now on.
+    pub fn add_structure(&mut self, schematic: Arc<Schematic>, origin: WorldPos) {
+        Arc::make_mut(&mut self.structures).push(Structure { schematic, origin });
+    }
+
+    fn gen_chunk(context: &Arc<GenerationContext>, structures: &[Structure], pos: &ChunkPos) -> Chunk {
         let mut chunk = Chunk::default();
         Self::generate_terrain(context, &mut chunk, pos);
+        for structure in structures {
+            structure.schematic.place_in_chunk(&mut chunk, pos, &structure.origin);
+        }
         chunk = Self::calc_internal_neighbors::<Wall, Chunk>(chunk);
         chunk = Self::calc_internal_neighbors::<Tile, Chunk>(chunk);
+        chunk = Self::calc_internal_neighbors::<Liquid, Chunk>(chunk);
         chunk
     }
 
@@ -109,9 +142,7 @@ impl WorldGenerator {
                     context.hell_transition_height as f64 +
                     context.cave_height as f64 +
                     context.noise.get_2d_range(tile_x, context.cave_transition_height, context.cave_transition_height, TERRAIN);
-            let terrain_height_line =
-                context.hell_lava as f64 + context.hell_transition_height as f64 + context.cave_transition_height as f64 + context.cave_height as f64 +
-                    context.noise.get_2d_range(tile_x, context.terrain_height, context.terrain_height, TERRAIN);
+            let terrain_height_line = Self::get_terrain_line(context, tile_x);
 
             for y in 0..CHUNK_SIZE {
                 let tile_y = (y as i32 + (pos.y as i32 * CHUNK_SIZE as i32)) as i32;
@@ -128,47 +159,19 @@ impl WorldGenerator {
         }
     }
 
-    pub fn calc_internal_neighbors<N: NeighborAware, G: Grid<N>>(owner: G) -> G {
-        let grid = owner.get_grid();
-        // update bottom right neighbors.
-        for y in 0..(CHUNK_SIZE - 1) {
-            let row = &grid[y];
-            let row_below = &grid[y + 1];
-            for x in 0..(CHUNK_SIZE - 1) {
-                let obj = &row[x];
-                let obj_after = &row[x + 1];
-                let obj_below = &row_below[x];
-                unsafe {
-                    // mutates the values!!!
-                    NeighborMatrix::update_neighbor(obj, obj_after, Direction::Right);
-                    NeighborMatrix::update_neighbor(obj, obj_below, Direction::Top);
-                }
-            }
-        };
-
-        // update right row
-        for y in 0..(CHUNK_SIZE - 1) {
-            let row = &grid[y];
-            let row_below = &grid[y + 1];
-            let obj = &row[CHUNK_SIZE - 1];
-            let obj_below = &row_below[CHUNK_SIZE - 1];
-            unsafe {
-                // mutates the values!!!
-                NeighborMatrix::update_neighbor(obj, obj_below, Direction::Top);
-            }
-        };
-
-        // update bottom column
-        for x in 0..(CHUNK_SIZE - 1) {
-            let row = &grid[CHUNK_SIZE - 1];
-            let obj = &row[x];
-            let obj_after = &row[x + 1];
-            unsafe {
-                // mutates the values!!!
-                NeighborMatrix::update_neighbor(obj, obj_after, Direction::Right);
-            }
-        };
+    /// Every tile below this line at `tile_x` is ground.
+    fn get_terrain_line(context: &GenerationContext, tile_x: i32) -> f64 {
+        context.hell_lava as f64 + context.hell_transition_height as f64 + context.cave_transition_height as f64 + context.cave_height as f64 +
+            context.noise.get_2d_range(tile_x, context.terrain_height, context.terrain_height, TERRAIN)
+    }
+
+    /// The y of the highest tile the terrain generates at `x`. Structures are not taken into account.
+    pub fn get_surface_height(&self, x: i32) -> u32 {
+        (Self::get_terrain_line(&self.context, x) as i32 - 1).max(0) as u32
+    }
 
+    pub fn calc_internal_neighbors<N: NeighborAware, G: Grid<N>>(mut owner: G) -> G {
+        NeighborMatrix::update_grid(owner.get_grid_mut());
         owner
     }
 }
\ No newline at end of file
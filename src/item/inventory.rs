@@ -0,0 +1,251 @@
+use std::io::{Read, Result, Write};
+
+use crate::item::ItemId;
+use crate::item::stack::ItemStack;
+use crate::world::save::Persistent;
+
+/// Slots of a player inventory. The first `HOTBAR_SLOTS` of them make up the hotbar.
+pub const PLAYER_SLOTS: usize = 50;
+pub const HOTBAR_SLOTS: usize = 10;
+
+/// A fixed amount of slots that can each hold a stack. Slots that are out of range are treated as empty and can not be changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inventory {
+	slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+	pub fn new(size: usize) -> Inventory {
+		Self { slots: vec![None; size] }
+	}
+
+	pub fn len(&self) -> usize {
+		self.slots.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.slots.iter().all(Option::is_none)
+	}
+
+	pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+		self.slots.get(slot).and_then(Option::as_ref)
+	}
+
+	pub fn get_slots(&self) -> &[Option<ItemStack>] {
+		&self.slots
+	}
+
+	/// Puts the stack into the slot and returns what was there before.
+	pub fn set(&mut self, slot: usize, stack: Option<ItemStack>) -> Option<ItemStack> {
+		match self.slots.get_mut(slot) {
+			Some(old) => std::mem::replace(old, stack.filter(|stack| !stack.is_empty())),
+			None => stack,
+		}
+	}
+
+	/// Adds the items to stacks of the same item first and then to the first empty slots. Returns what did not fit.
+	pub fn insert(&mut self, stack: ItemStack) -> Option<ItemStack> {
+		let mut rest = Some(stack).filter(|stack| !stack.is_empty());
+		for slot in self.slots.iter_mut().flatten() {
+			rest = match rest {
+				Some(stack) => slot.merge(stack),
+				None => return None,
+			};
+		}
+
+		for slot in &mut self.slots {
+			if let (None, Some(stack)) = (&slot, rest) {
+				let mut placed = ItemStack::new(stack.id, 0);
+				rest = placed.merge(stack);
+				*slot = Some(placed);
+			}
+		}
+		rest
+	}
+
+	/// Takes up to `count` items out of the slot.
+	pub fn take(&mut self, slot: usize, count: u16) -> Option<ItemStack> {
+		let stack = self.slots.get_mut(slot)?;
+		let taken = stack.as_mut()?.split(count);
+		if matches!(stack, Some(stack) if stack.is_empty()) {
+			*stack = None;
+		}
+		taken
+	}
+
+	/// Moves as many items from `from` onto the stack in `to` as fit. Both slots need to hold the same item or `to` needs to be empty.
+	pub fn merge(&mut self, from: usize, to: usize) -> bool {
+		if from == to || to >= self.slots.len() {
+			return false;
+		}
+		let stack = match self.get(from) {
+			Some(stack) => *stack,
+			None => return false,
+		};
+
+		let rest = match &mut self.slots[to] {
+			Some(target) if target.id == stack.id => target.merge(stack),
+			Some(_) => return false,
+			target @ None => {
+				*target = Some(stack);
+				None
+			}
+		};
+		self.slots[from] = rest;
+		true
+	}
+
+	/// Moves the stack in `from` onto `to`. Stacks of the same item merge and anything else swaps places.
+	pub fn move_stack(&mut self, from: usize, to: usize) {
+		if from == to || from >= self.slots.len() || to >= self.slots.len() {
+			return;
+		}
+		if !self.merge(from, to) {
+			self.slots.swap(from, to);
+		}
+	}
+
+	/// Moves half of the stack in `from`, rounded up, into the empty slot `to`.
+	pub fn split(&mut self, from: usize, to: usize) -> bool {
+		if from == to || !matches!(self.slots.get(to), Some(None)) {
+			return false;
+		}
+		let half = match self.get(from) {
+			Some(stack) => stack.count.div_ceil(2),
+			None => return false,
+		};
+		let taken = self.take(from, half);
+		self.slots[to] = taken;
+		true
+	}
+
+	/// How many of `id` are in the inventory over all slots.
+	pub fn count(&self, id: &ItemId) -> u32 {
+		self.slots.iter().flatten().filter(|stack| stack.id == *id).map(|stack| stack.count as u32).sum()
+	}
+
+	/// Removes `count` items of `id` from any slots, the last slots first.
+	/// Removes nothing and returns false if there are not enough of them.
+	pub fn remove(&mut self, id: &ItemId, count: u32) -> bool {
+		if self.count(id) < count {
+			return false;
+		}
+
+		let mut left = count;
+		for slot in (0..self.slots.len()).rev() {
+			if left == 0 {
+				break;
+			}
+			if matches!(self.get(slot), Some(stack) if stack.id == *id) {
+				let taken = self.take(slot, left.min(u16::MAX as u32) as u16);
+				left -= taken.map_or(0, |taken| taken.count as u32);
+			}
+		}
+		true
+	}
+}
+
+impl Persistent for Inventory {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		(self.slots.len() as u16).write(out)?;
+		for slot in &self.slots {
+			match slot {
+				Some(stack) => {
+					1u8.write(out)?;
+					stack.write(out)?;
+				}
+				None => 0u8.write(out)?,
+			}
+		}
+		Ok(())
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let len = u16::read(input)?;
+		let mut slots = Vec::with_capacity(len as usize);
+		for _ in 0..len {
+			slots.push(match u8::read(input)? {
+				0 => None,
+				_ => Some(ItemStack::read(input)?),
+			});
+		}
+		Ok(Inventory { slots })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::item::{COPPER_PICKAXE, IRON_BAR, MATERIAL_STACK};
+	use crate::item::inventory::Inventory;
+	use crate::item::stack::ItemStack;
+
+	#[test]
+	fn insert_fills_stacks_before_empty_slots() {
+		let mut inventory = Inventory::new(3);
+		inventory.set(2, Some(ItemStack::new(IRON_BAR, MATERIAL_STACK - 5)));
+		assert_eq!(inventory.insert(ItemStack::new(IRON_BAR, 10)), None);
+		assert_eq!(inventory.get(2), Some(&ItemStack::new(IRON_BAR, MATERIAL_STACK)));
+		assert_eq!(inventory.get(0), Some(&ItemStack::new(IRON_BAR, 5)));
+
+		// Pickaxes do not stack, so only one of them fits into the last slot.
+		assert_eq!(inventory.insert(ItemStack::new(COPPER_PICKAXE, 2)), Some(ItemStack::new(COPPER_PICKAXE, 1)));
+		assert_eq!(inventory.insert(ItemStack::new(IRON_BAR, 200)), Some(ItemStack::new(IRON_BAR, 200 - (MATERIAL_STACK - 5))));
+		assert_eq!(inventory.insert(ItemStack::new(IRON_BAR, 0)), None);
+	}
+
+	#[test]
+	fn merge_moves_what_fits() {
+		let mut inventory = Inventory::new(3);
+		inventory.set(0, Some(ItemStack::new(IRON_BAR, 60)));
+		inventory.set(1, Some(ItemStack::new(IRON_BAR, 60)));
+		inventory.set(2, Some(ItemStack::new(COPPER_PICKAXE, 1)));
+
+		assert!(inventory.merge(0, 1));
+		assert_eq!(inventory.get(0), Some(&ItemStack::new(IRON_BAR, 120 - MATERIAL_STACK)));
+		assert_eq!(inventory.get(1), Some(&ItemStack::new(IRON_BAR, MATERIAL_STACK)));
+		assert!(!inventory.merge(0, 2));
+		assert!(!inventory.merge(0, 0));
+		assert!(!inventory.merge(0, 3));
+
+		// Moving onto a different item swaps them instead.
+		inventory.move_stack(0, 2);
+		assert_eq!(inventory.get(0), Some(&ItemStack::new(COPPER_PICKAXE, 1)));
+		assert_eq!(inventory.get(2), Some(&ItemStack::new(IRON_BAR, 120 - MATERIAL_STACK)));
+	}
+
+	#[test]
+	fn split_moves_half_rounded_up() {
+		let mut inventory = Inventory::new(3);
+		inventory.set(0, Some(ItemStack::new(IRON_BAR, 7)));
+		inventory.set(2, Some(ItemStack::new(IRON_BAR, 1)));
+
+		assert!(inventory.split(0, 1));
+		assert_eq!(inventory.get(0), Some(&ItemStack::new(IRON_BAR, 3)));
+		assert_eq!(inventory.get(1), Some(&ItemStack::new(IRON_BAR, 4)));
+		// Only empty slots can take the other half.
+		assert!(!inventory.split(0, 2));
+		assert!(!inventory.split(2, 0));
+
+		let mut inventory = Inventory::new(2);
+		inventory.set(0, Some(ItemStack::new(IRON_BAR, 1)));
+		assert!(inventory.split(0, 1));
+		assert_eq!(inventory.get(0), None);
+		assert_eq!(inventory.get(1), Some(&ItemStack::new(IRON_BAR, 1)));
+	}
+
+	#[test]
+	fn remove_takes_from_the_last_slots_or_nothing() {
+		let mut inventory = Inventory::new(3);
+		inventory.set(0, Some(ItemStack::new(IRON_BAR, 10)));
+		inventory.set(2, Some(ItemStack::new(IRON_BAR, 4)));
+
+		assert!(!inventory.remove(&IRON_BAR, 15));
+		assert_eq!(inventory.count(&IRON_BAR), 14);
+
+		assert!(inventory.remove(&IRON_BAR, 6));
+		assert_eq!(inventory.get(2), None);
+		assert_eq!(inventory.get(0), Some(&ItemStack::new(IRON_BAR, 8)));
+		assert!(inventory.remove(&IRON_BAR, 8));
+		assert!(inventory.is_empty());
+	}
+}
@@ -0,0 +1,137 @@
+use std::io::{Read, Result, Write};
+
+use crate::world::save::{invalid_data, Persistent};
+use crate::world::tile;
+use crate::world::tile::TileId;
+
+pub mod stack;
+pub mod inventory;
+pub mod recipe;
+
+/// How many blocks fit into one stack.
+pub const BLOCK_STACK: u16 = 999;
+/// How many materials fit into one stack.
+pub const MATERIAL_STACK: u16 = 99;
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Ord, PartialOrd)]
+pub enum ItemId {
+	/// Places its tile. Every named tile has a block item.
+	Block(TileId),
+	/// Tools, materials and everything else that is not a block.
+	Item(u32),
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ItemKind {
+	Block(TileId),
+	/// Mines tiles that need at most `power`, hitting every `use_time` ticks.
+	Tool { power: u32, use_time: u32 },
+	/// Only good for crafting.
+	Material,
+}
+
+/// What an item does and how many of it fit into one stack.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ItemType {
+	pub id: ItemId,
+	pub kind: ItemKind,
+	pub max_stack: u16,
+}
+
+macro_rules! items {
+    ($($NAME:ident $ID:literal => $KIND:expr, $STACK:expr;)*) => {
+        $(pub const $NAME: ItemId = ItemId::Item($ID);)*
+
+        /// Every item that is not a block with the key it gets saved as in files that outlive id changes.
+        pub const ITEM_KEYS: &[(&str, ItemId)] = &[$((stringify!($NAME), $NAME),)*];
+
+        impl ItemType {
+            /// Gets the type of `id`, if `id` is a known item. Block items come from the tile definitions.
+            pub fn get(id: &ItemId) -> Option<ItemType> {
+                match *id {
+                    ItemId::Block(tile) => ItemType::get_block(tile),
+                    $($NAME => Some(ItemType { id: $NAME, kind: $KIND, max_stack: $STACK }),)*
+                    ItemId::Item(_) => None,
+                }
+            }
+        }
+    };
+}
+
+items! {
+	COPPER_PICKAXE 1 => ItemKind::Tool { power: 35, use_time: 15 }, 1;
+	IRON_PICKAXE 2 => ItemKind::Tool { power: 40, use_time: 13 }, 1;
+	SILVER_PICKAXE 3 => ItemKind::Tool { power: 45, use_time: 12 }, 1;
+	GOLD_PICKAXE 4 => ItemKind::Tool { power: 55, use_time: 11 }, 1;
+	NIGHTMARE_PICKAXE 5 => ItemKind::Tool { power: 65, use_time: 10 }, 1;
+	MOLTEN_PICKAXE 6 => ItemKind::Tool { power: 100, use_time: 11 }, 1;
+	COPPER_BAR 20 => ItemKind::Material, MATERIAL_STACK;
+	IRON_BAR 21 => ItemKind::Material, MATERIAL_STACK;
+	SILVER_BAR 22 => ItemKind::Material, MATERIAL_STACK;
+	GOLD_BAR 23 => ItemKind::Material, MATERIAL_STACK;
+	DEMONITE_BAR 24 => ItemKind::Material, MATERIAL_STACK;
+	METEORITE_BAR 25 => ItemKind::Material, MATERIAL_STACK;
+	HELLSTONE_BAR 26 => ItemKind::Material, MATERIAL_STACK;
+	GEL 27 => ItemKind::Material, MATERIAL_STACK;
+}
+
+impl ItemType {
+	fn get_block(tile: TileId) -> Option<ItemType> {
+		if tile == tile::AIR || tile.get_key().is_none() {
+			return None;
+		}
+		Some(ItemType { id: ItemId::Block(tile), kind: ItemKind::Block(tile), max_stack: BLOCK_STACK })
+	}
+}
+
+impl ItemId {
+	pub fn get_type(&self) -> Option<ItemType> {
+		ItemType::get(self)
+	}
+
+	/// The key of this item. Blocks use the key of their tile.
+	pub fn get_key(&self) -> Option<&'static str> {
+		match self {
+			ItemId::Block(tile) => tile.get_key(),
+			ItemId::Item(_) => ITEM_KEYS.iter().find(|(_, id)| id == self).map(|(key, _)| *key),
+		}
+	}
+
+	pub fn from_key(key: &str) -> Option<ItemId> {
+		ITEM_KEYS.iter().find(|(name, _)| *name == key).map(|(_, id)| *id)
+			.or_else(|| TileId::from_key(key).map(ItemId::Block))
+	}
+
+	/// The item a broken tile turns into. Grass drops the block it grows on and plants drop nothing.
+	pub fn from_tile_drop(id: &TileId) -> Option<ItemId> {
+		match *id {
+			tile::AIR | tile::REGULAR_VINE | tile::JUNGLE_VINE | tile::JUNGLE_THORNY_BUSH => None,
+			tile::GRASS | tile::CORRUPT_GRASS | tile::HALLOWED_GRASS => Some(ItemId::Block(tile::DIRT)),
+			tile::JUNGLE_GRASS | tile::MUSHROOM_GRASS => Some(ItemId::Block(tile::MUD_BLOCK)),
+			_ => Some(ItemId::Block(*id)),
+		}
+	}
+}
+
+impl Persistent for ItemId {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		match self {
+			ItemId::Block(tile) => {
+				0u8.write(out)?;
+				tile.write(out)
+			}
+			ItemId::Item(id) => {
+				1u8.write(out)?;
+				id.write(out)
+			}
+		}
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		match u8::read(input)? {
+			0 => Ok(ItemId::Block(TileId::read(input)?)),
+			1 => Ok(ItemId::Item(u32::read(input)?)),
+			kind => Err(invalid_data(format!("Unknown item kind {}", kind))),
+		}
+	}
+}
@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+use crate::item::ItemId;
+use crate::item::inventory::Inventory;
+use crate::item::stack::ItemStack;
+use crate::world::save::invalid_data;
+use crate::world::tile::TileId;
+
+/// How far away a crafting station can be from the center of the player, in tiles.
+pub const CRAFTING_RANGE_X: u32 = 5;
+pub const CRAFTING_RANGE_Y: u32 = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recipe {
+	pub output: ItemStack,
+	/// Every input item appears only once.
+	pub inputs: Vec<ItemStack>,
+	/// Tiles that all need to be nearby.
+	pub stations: Vec<TileId>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CraftError {
+	UnknownRecipe,
+	MissingInputs,
+	MissingStation(TileId),
+	/// The output does not fit into the inventory.
+	NoSpace,
+}
+
+impl Recipe {
+	pub fn has_inputs(&self, inventory: &Inventory) -> bool {
+		self.inputs.iter().all(|input| inventory.count(&input.id) >= input.count as u32)
+	}
+
+	/// The first station that is not in `stations`.
+	pub fn get_missing_station(&self, stations: &HashSet<TileId>) -> Option<TileId> {
+		self.stations.iter().find(|station| !stations.contains(station)).copied()
+	}
+
+	pub fn can_craft(&self, inventory: &Inventory, stations: &HashSet<TileId>) -> bool {
+		self.has_inputs(inventory) && self.get_missing_station(stations).is_none()
+	}
+
+	/// Takes the inputs out of the inventory and puts the output in. The inventory only changes if everything worked out.
+	pub fn craft(&self, inventory: &mut Inventory, stations: &HashSet<TileId>) -> std::result::Result<(), CraftError> {
+		if let Some(station) = self.get_missing_station(stations) {
+			return Err(CraftError::MissingStation(station));
+		}
+
+		let mut result = inventory.clone();
+		for input in &self.inputs {
+			if !result.remove(&input.id, input.count as u32) {
+				return Err(CraftError::MissingInputs);
+			}
+		}
+		if result.insert(self.output).is_some() {
+			return Err(CraftError::NoSpace);
+		}
+		*inventory = result;
+		Ok(())
+	}
+}
+
+/// Every recipe, loaded from a text file with one recipe per line:
+/// `<output> [count] = <input> [count], <input> [count] @ <station>, <station>`.
+/// Items and stations are named by their keys, counts default to 1 and `#` starts a comment.
+#[derive(Clone, Debug, Default)]
+pub struct RecipeRegistry {
+	recipes: Vec<Recipe>,
+}
+
+impl RecipeRegistry {
+	pub fn load(path: &Path) -> Result<RecipeRegistry> {
+		Self::parse(&fs::read_to_string(path)?)
+	}
+
+	pub fn parse(text: &str) -> Result<RecipeRegistry> {
+		let mut recipes = Vec::new();
+		for (index, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or("").trim();
+			if !line.is_empty() {
+				recipes.push(parse_recipe(line).map_err(|error| invalid_data(format!("Line {}: {}", index + 1, error)))?);
+			}
+		}
+		Ok(RecipeRegistry { recipes })
+	}
+
+	/// Recipes are identified by their index, which stays the same as long as the file does not change.
+	pub fn get(&self, recipe: usize) -> Option<&Recipe> {
+		self.recipes.get(recipe)
+	}
+
+	pub fn get_recipes(&self) -> &[Recipe] {
+		&self.recipes
+	}
+
+	/// Every recipe that makes `id`.
+	pub fn get_recipes_for<'a>(&'a self, id: &'a ItemId) -> impl Iterator<Item = (usize, &'a Recipe)> + 'a {
+		self.recipes.iter().enumerate().filter(move |(_, recipe)| recipe.output.id == *id)
+	}
+
+	/// Every recipe that can be crafted right now with the inventory and the stations.
+	pub fn get_craftable(&self, inventory: &Inventory, stations: &HashSet<TileId>) -> Vec<usize> {
+		self.recipes.iter().enumerate()
+			.filter(|(_, recipe)| recipe.can_craft(inventory, stations))
+			.map(|(index, _)| index)
+			.collect()
+	}
+}
+
+fn parse_recipe(line: &str) -> Result<Recipe> {
+	let (output, rest) = line.split_once('=').ok_or_else(|| invalid_data("Missing =".to_string()))?;
+	let (inputs, stations) = rest.split_once('@').unwrap_or((rest, ""));
+
+	let mut merged: Vec<ItemStack> = Vec::new();
+	for input in inputs.split(',') {
+		let input = parse_stack(input)?;
+		match merged.iter_mut().find(|stack| stack.id == input.id) {
+			Some(stack) => stack.count = stack.count.checked_add(input.count).ok_or_else(|| invalid_data("Too many inputs".to_string()))?,
+			None => merged.push(input),
+		}
+	}
+
+	let stations = stations.split(',').map(str::trim).filter(|key| !key.is_empty())
+		.map(|key| TileId::from_key(key).ok_or_else(|| invalid_data(format!("Unknown station {}", key))))
+		.collect::<Result<Vec<TileId>>>()?;
+	Ok(Recipe { output: parse_stack(output)?, inputs: merged, stations })
+}
+
+/// Reads `<key> [count]`.
+fn parse_stack(text: &str) -> Result<ItemStack> {
+	let mut parts = text.split_whitespace();
+	let key = parts.next().ok_or_else(|| invalid_data("Missing item".to_string()))?;
+	let id = ItemId::from_key(key).filter(|id| id.get_type().is_some()).ok_or_else(|| invalid_data(format!("Unknown item {}", key)))?;
+	let count = match parts.next() {
+		Some(count) => count.parse::<u16>().map_err(|_| invalid_data(format!("Invalid count {}", count)))?,
+		None => 1,
+	};
+
+	if count == 0 {
+		return Err(invalid_data(format!("{} needs a count above 0", key)));
+	}
+	if let Some(extra) = parts.next() {
+		return Err(invalid_data(format!("Unexpected {}", extra)));
+	}
+	Ok(ItemStack::new(id, count))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+
+	use crate::item::{IRON_BAR, ItemId};
+	use crate::item::inventory::Inventory;
+	use crate::item::recipe::{CraftError, RecipeRegistry};
+	use crate::item::stack::ItemStack;
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::player::Player;
+	use crate::world::save::Persistent;
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile;
+
+	const RECIPES: &str = "WORK_BENCH = WOOD 10\nTABLE = WOOD 8 @ WORK_BENCH # A comment\n\nCHEST = WOOD 6, IRON_BAR 2, WOOD 3\n";
+
+	fn create_inventory(slots: &[ItemStack], size: usize) -> Inventory {
+		let mut inventory = Inventory::new(size);
+		for (slot, stack) in slots.iter().enumerate() {
+			inventory.set(slot, Some(*stack));
+		}
+		inventory
+	}
+
+	fn to_bytes(inventory: &Inventory) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		inventory.write(&mut bytes).unwrap();
+		bytes
+	}
+
+	#[test]
+	fn malformed_lines_get_rejected_with_their_line_number() {
+		for (text, message) in [
+			("WORK_BENCH = WOOD 10\n\nTABLE WOOD 8", "Line 3: Missing ="),
+			("# Comment\nTABLE = PLANK 8", "Line 2: Unknown item PLANK"),
+			("TABLE = WOOD 0", "Line 1: WOOD needs a count above 0"),
+			("TABLE = WOOD many", "Line 1: Invalid count many"),
+			("TABLE = WOOD 8 8", "Line 1: Unexpected 8"),
+			("TABLE = WOOD 8 @ WORK_BENCH, TABLE_SAW", "Line 1: Unknown station TABLE_SAW"),
+			("TABLE = WOOD 8,", "Line 1: Missing item"),
+		] {
+			let error = RecipeRegistry::parse(text).unwrap_err();
+			assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+			assert_eq!(error.to_string(), message);
+		}
+	}
+
+	#[test]
+	fn parse_merges_inputs_and_skips_comments() {
+		let recipes = RecipeRegistry::parse("WORK_BENCH = WOOD 6, WOOD 4 # Comment\n# TABLE = WOOD 8").unwrap();
+		assert_eq!(recipes.get_recipes().len(), 1);
+		assert_eq!(recipes.get(0).unwrap().inputs, vec![ItemStack::new(ItemId::Block(tile::WOOD), 10)]);
+	}
+
+	#[test]
+	fn station_recipes_are_hidden_unless_the_station_is_in_range() {
+		let floor = |_, y| if y == 0 { tile::STONE } else { tile::AIR };
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(floor)), (ChunkPos::new(1, 0), create_chunk(floor))]);
+		world.set_recipes(RecipeRegistry::parse("WORK_BENCH = WOOD 10\nTABLE = WOOD 8 @ WORK_BENCH").unwrap());
+		world.place_object(&WorldPos::new(4, 1), tile::WORK_BENCH).unwrap();
+
+		let mut player = Player::new();
+		player.pos_x = 20.5;
+		player.pos_y = 1.0;
+		player.inventory = create_inventory(&[ItemStack::new(ItemId::Block(tile::WOOD), 20)], 3);
+		let id = world.player_join(player);
+		assert_eq!(world.get_craftable_recipes(&id), vec![0]);
+		assert_eq!(world.craft(&id, 1), Err(CraftError::MissingStation(tile::WORK_BENCH)));
+
+		world.acquire_player_mut(&id).pos_x = 9.5;
+		assert_eq!(world.get_craftable_recipes(&id), vec![0, 1]);
+		assert_eq!(world.craft(&id, 1), Ok(()));
+		assert_eq!(world.craft(&id, 2), Err(CraftError::UnknownRecipe));
+	}
+
+	#[test]
+	fn failed_crafts_leave_the_inventory_unchanged() {
+		let recipes = RecipeRegistry::parse(RECIPES).unwrap();
+		let stations = HashSet::from([tile::WORK_BENCH]);
+		let wood = ItemId::Block(tile::WOOD);
+
+		let mut inventory = create_inventory(&[ItemStack::new(wood, 5), ItemStack::new(IRON_BAR, 5), ItemStack::new(wood, 2)], 4);
+		let before = to_bytes(&inventory);
+		assert_eq!(recipes.get(1).unwrap().craft(&mut inventory, &stations), Err(CraftError::MissingInputs));
+		assert_eq!(to_bytes(&inventory), before);
+
+		// The wood is only partly used up, so the table has nowhere to go.
+		let mut inventory = create_inventory(&[ItemStack::new(wood, 10), ItemStack::new(IRON_BAR, 5)], 2);
+		let before = to_bytes(&inventory);
+		assert_eq!(recipes.get(1).unwrap().craft(&mut inventory, &stations), Err(CraftError::NoSpace));
+		assert_eq!(to_bytes(&inventory), before);
+
+		assert_eq!(recipes.get(1).unwrap().craft(&mut inventory, &HashSet::new()), Err(CraftError::MissingStation(tile::WORK_BENCH)));
+		assert_eq!(to_bytes(&inventory), before);
+	}
+
+	#[test]
+	fn crafting_consumes_exactly_the_inputs() {
+		let recipes = RecipeRegistry::parse(RECIPES).unwrap();
+		let wood = ItemId::Block(tile::WOOD);
+		let mut inventory = create_inventory(&[ItemStack::new(wood, 5), ItemStack::new(IRON_BAR, 5), ItemStack::new(wood, 5)], 4);
+
+		assert_eq!(recipes.get_craftable(&inventory, &HashSet::new()), vec![0, 2]);
+		assert_eq!(recipes.get(2).unwrap().craft(&mut inventory, &HashSet::new()), Ok(()));
+		assert_eq!(inventory.count(&wood), 10 - 9);
+		assert_eq!(inventory.count(&IRON_BAR), 5 - 2);
+		assert_eq!(inventory.count(&ItemId::Block(tile::CHEST)), 1);
+		assert_eq!(inventory.get_slots().iter().flatten().map(|stack| stack.count as u32).sum::<u32>(), 1 + 3 + 1);
+	}
+}
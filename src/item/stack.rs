@@ -0,0 +1,67 @@
+use std::io::{Read, Result, Write};
+
+use crate::item::{ItemId, ItemType};
+use crate::world::save::Persistent;
+
+/// An amount of a single item, as held in an inventory slot.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ItemStack {
+	pub id: ItemId,
+	pub count: u16,
+}
+
+impl ItemStack {
+	pub fn new(id: ItemId, count: u16) -> ItemStack {
+		Self { id, count }
+	}
+
+	pub fn get_type(&self) -> Option<ItemType> {
+		ItemType::get(&self.id)
+	}
+
+	/// How many of this item fit into one stack. Unknown items do not stack.
+	pub fn get_max_stack(&self) -> u16 {
+		self.get_type().map_or(1, |item| item.max_stack)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.count == 0
+	}
+
+	pub fn is_full(&self) -> bool {
+		self.count >= self.get_max_stack()
+	}
+
+	/// If some of `other` fits onto this stack.
+	pub fn can_merge(&self, other: &ItemStack) -> bool {
+		self.id == other.id && !self.is_full()
+	}
+
+	/// Moves as many items of `other` onto this stack as fit. Returns what is left of `other`.
+	pub fn merge(&mut self, mut other: ItemStack) -> Option<ItemStack> {
+		if self.id == other.id {
+			let moved = other.count.min(self.get_max_stack().saturating_sub(self.count));
+			self.count += moved;
+			other.count -= moved;
+		}
+		Some(other).filter(|other| !other.is_empty())
+	}
+
+	/// Takes up to `count` items off this stack, which can leave it empty.
+	pub fn split(&mut self, count: u16) -> Option<ItemStack> {
+		let count = count.min(self.count);
+		self.count -= count;
+		Some(ItemStack::new(self.id, count)).filter(|stack| !stack.is_empty())
+	}
+}
+
+impl Persistent for ItemStack {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		self.id.write(out)?;
+		self.count.write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		Ok(ItemStack::new(ItemId::read(input)?, u16::read(input)?))
+	}
+}
@@ -1,14 +1,19 @@
 use std::fs::File;
 use std::io::Read;
 use std::ops::Div;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use crate::client::ClientHandler;
+use crate::item::recipe::RecipeRegistry;
 use crate::player::Player;
+use crate::world::paint::PaintPalette;
+use crate::world::save::WorldSave;
 use crate::world::World;
 
 pub mod player;
 pub mod world;
+pub mod item;
 pub mod client;
 pub mod settings;
 mod network;
@@ -23,11 +28,23 @@ fn main() {
 
 const MS_PER_UPDATE: f64 = 1000.0 / 60.0;
 const MS_PER_PROFILE_PRINT: u128 = 1000;
+const SAVE_DIR: &str = "./saves/world";
 
 fn run_rustaria() {
     println!("Launching Rustaria. This is gonna be rusty.");
     let mut client: ClientHandler = client::ClientHandler::create();
-    let world = World::default();
+    let mut world = World::default();
+    match RecipeRegistry::load(Path::new("./assets/data/recipes.txt")) {
+        Ok(recipes) => world.set_recipes(recipes),
+        Err(error) => println!("Could not load recipes. {}", error),
+    }
+    match PaintPalette::load(Path::new("./assets/data/paints.txt")) {
+        Ok(paints) => world.set_paints(paints),
+        Err(error) => println!("Could not load paints. {}", error),
+    }
+    if let Err(error) = world.set_save(WorldSave::new(PathBuf::from(SAVE_DIR))) {
+        println!("Could not read the pending edits of the save, they got moved aside and will not be applied. {}", error);
+    }
     client.join_world(world);
 
     let mut profiler = Profiler {
@@ -40,7 +57,7 @@ fn run_rustaria() {
 
     let mut previous_update = Instant::now();
     let mut lag = 0f64;
-    loop {
+    while !client.should_close() {
         let elapsed = previous_update.elapsed();
         previous_update = Instant::now();
         lag += elapsed.as_micros() as f64 / 1000.0;
@@ -61,6 +78,12 @@ fn run_rustaria() {
         profiler.frames += 1;
         profiler.update();
     }
+
+    if let Some(world) = client.leave_world() {
+        if let Err(error) = world.save() {
+            println!("Could not save the world. {}", error);
+        }
+    }
 }
 
 
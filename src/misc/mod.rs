@@ -1,3 +1,4 @@
 pub mod random_quote;
 pub mod util;
-pub mod pos;
\ No newline at end of file
+pub mod pos;
+pub mod random;
\ No newline at end of file
@@ -1,6 +1,8 @@
 use crate::player::Player;
 use crate::misc::util::{CHUNK_SIZE, Direction};
 
+/// A tile in the world. The world spans every chunk a `ChunkPos` can address,
+/// so x goes from `WorldPos::MIN_X` to `WorldPos::MAX_X` and y from 0 to `WorldPos::MAX_Y`.
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 pub struct WorldPos {
 	chunk_pos: ChunkPos,
@@ -19,8 +21,24 @@ pub struct ChunkSubPos {
 	pub y: u8,
 }
 
+/// A continuous position in tile units, used for players and everything else that moves freely.
+/// The tile at `WorldPos::new(x, y)` covers `x..x + 1` and `y..y + 1`.
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct FloatPos {
+	pub x: f32,
+	pub y: f32,
+}
+
 impl WorldPos {
+	pub const MIN_X: i32 = i16::MIN as i32 * CHUNK_SIZE as i32;
+	pub const MAX_X: i32 = i16::MAX as i32 * CHUNK_SIZE as i32 + CHUNK_SIZE as i32 - 1;
+	pub const MIN_Y: u32 = 0;
+	pub const MAX_Y: u32 = u16::MAX as u32 * CHUNK_SIZE as u32 + CHUNK_SIZE as u32 - 1;
+
+	/// Creates the position, which needs to be inside of the world. Use `checked_new` for positions that might not be.
 	pub fn new(x: i32, y: u32) -> WorldPos {
+		debug_assert!((Self::MIN_X..=Self::MAX_X).contains(&x), "X {} is outside of the world.", x);
+		debug_assert!(y <= Self::MAX_Y, "Y {} is outside of the world.", y);
 		let chunk_x = x.div_euclid(CHUNK_SIZE as i32) as i16;
 		let chunk_y = y.div_euclid(CHUNK_SIZE as u32) as u16;
 
@@ -32,6 +50,26 @@ impl WorldPos {
 		}
 	}
 
+	/// The position if it is inside of the world.
+	pub fn checked_new(x: i64, y: i64) -> Option<WorldPos> {
+		if (Self::MIN_X as i64..=Self::MAX_X as i64).contains(&x) && (Self::MIN_Y as i64..=Self::MAX_Y as i64).contains(&y) {
+			Some(Self::new(x as i32, y as u32))
+		} else {
+			None
+		}
+	}
+
+	/// The position wrapped around the edges of the world on both axes.
+	pub fn wrapping_new(x: i64, y: i64) -> WorldPos {
+		// Wide enough that moving any i64 by the world edge cannot overflow.
+		let width = Self::MAX_X as i128 - Self::MIN_X as i128 + 1;
+		let height = Self::MAX_Y as i128 - Self::MIN_Y as i128 + 1;
+		Self::new(
+			((x as i128 - Self::MIN_X as i128).rem_euclid(width) + Self::MIN_X as i128) as i32,
+			((y as i128 - Self::MIN_Y as i128).rem_euclid(height) + Self::MIN_Y as i128) as u32,
+		)
+	}
+
 	pub fn from_chunk(chunk_pos: &ChunkPos, x: u8, y: u8) -> WorldPos {
 		Self {
 			chunk_pos: *chunk_pos,
@@ -47,6 +85,41 @@ impl WorldPos {
 		&self.chunk_sub_pos
 	}
 
+	/// The x position in tiles.
+	pub fn get_x(&self) -> i32 {
+		self.chunk_pos.x as i32 * CHUNK_SIZE as i32 + self.chunk_sub_pos.x as i32
+	}
+
+	/// The y position in tiles.
+	pub fn get_y(&self) -> u32 {
+		self.chunk_pos.y as u32 * CHUNK_SIZE as u32 + self.chunk_sub_pos.y as u32
+	}
+
+	/// The position moved by `x` and `y` tiles, if that is still inside of the world.
+	pub fn checked_add(&self, x: i32, y: i32) -> Option<WorldPos> {
+		Self::checked_new(self.get_x() as i64 + x as i64, self.get_y() as i64 + y as i64)
+	}
+
+	/// The position moved by `x` and `y` tiles, wrapping around the edges of the world.
+	pub fn wrapping_add(&self, x: i32, y: i32) -> WorldPos {
+		Self::wrapping_new(self.get_x() as i64 + x as i64, self.get_y() as i64 + y as i64)
+	}
+
+	/// How far `other` is from this position on both axes.
+	pub fn get_offset(&self, other: &WorldPos) -> (i64, i64) {
+		(other.get_x() as i64 - self.get_x() as i64, other.get_y() as i64 - self.get_y() as i64)
+	}
+
+	pub fn get_distance_squared(&self, other: &WorldPos) -> i64 {
+		let (x, y) = self.get_offset(other);
+		x * x + y * y
+	}
+
+	/// The center of the tile.
+	pub fn get_center(&self) -> FloatPos {
+		FloatPos::new(self.get_x() as f32 + 0.5, self.get_y() as f32 + 0.5)
+	}
+
 	pub fn shift(&self, direction: Direction) -> Option<WorldPos> {
 		if self.chunk_sub_pos.is_border(direction) {
 			let chunk_sub_pos = self.chunk_sub_pos.shift_overlooping(direction);
@@ -57,6 +130,12 @@ impl WorldPos {
 			})
 		}
 	}
+
+	/// The positions next to this one that are inside of the world, with the direction they are in.
+	pub fn get_neighbors(&self) -> impl Iterator<Item = (Direction, WorldPos)> {
+		let pos = *self;
+		Direction::iter().into_iter().filter_map(move |direction| pos.shift(direction).map(|neighbor| (direction, neighbor)))
+	}
 }
 
 impl ChunkPos {
@@ -68,25 +147,29 @@ impl ChunkPos {
 	}
 
 	pub fn from_player(player: &Player) -> ChunkPos {
-		let chunk_x = (player.pos_x as f32 / CHUNK_SIZE as f32).floor() as i16;
-		let chunk_y = (player.pos_y as f32 / CHUNK_SIZE as f32).floor() as u16;
-		ChunkPos::new(chunk_x, chunk_y)
+		FloatPos::new(player.pos_x, player.pos_y).get_chunk_pos()
 	}
 
 	pub fn shift(&self, direction: Direction) -> Option<ChunkPos> {
 		self.shift_amount(direction, 1)
 	}
 
+	/// The chunk `amount` chunks away in `direction`, if it is inside of the world.
 	pub fn shift_amount(&self, direction: Direction, amount: i32) -> Option<ChunkPos> {
-		let next_x = self.x as i32 + (direction.get_x_difference() as i32 * amount);
-		let next_y = self.y as i32 + (direction.get_y_difference() as i32 * amount);
-		if next_y <= u16::MIN as i32 || next_y >= u16::MAX as i32 || next_x <= i16::MIN as i32 || next_x >= i16::MAX as i32 {
-			return None;
-		}
-		let mut pos = *self;
-		pos.x = next_x as i16;
-		pos.y = next_y as u16;
-		Some(pos)
+		let next_x = self.x as i64 + direction.get_x_difference() as i64 * amount as i64;
+		let next_y = self.y as i64 + direction.get_y_difference() as i64 * amount as i64;
+		Some(ChunkPos::new(i16::try_from(next_x).ok()?, u16::try_from(next_y).ok()?))
+	}
+
+	/// The bottom left tile of the chunk.
+	pub fn get_origin(&self) -> WorldPos {
+		WorldPos::from_chunk(self, 0, 0)
+	}
+
+	/// Every tile of the chunk, row by row starting at the bottom left.
+	pub fn iter_positions(&self) -> impl Iterator<Item = WorldPos> {
+		let pos = *self;
+		ChunkSubPos::iter().map(move |sub_pos| WorldPos::from_chunk(&pos, sub_pos.x, sub_pos.y))
 	}
 }
 
@@ -101,24 +184,26 @@ impl ChunkSubPos {
 		}
 	}
 
-	pub fn new_overlooping(mut x: i8, mut y: i8) -> ChunkSubPos {
-		if x >= CHUNK_SIZE as i8 {
-			x = 0;
-		}
-		if y >= CHUNK_SIZE as i8 {
-			y = 0;
-		}
-		if x < 0_i8 {
-			x = (CHUNK_SIZE - 1) as i8;
-		}
-		if y < 0_i8 {
-			y = (CHUNK_SIZE - 1) as i8;
+	/// The position if both axes are inside of the chunk.
+	pub fn checked_new(x: i32, y: i32) -> Option<ChunkSubPos> {
+		if (0..CHUNK_SIZE as i32).contains(&x) && (0..CHUNK_SIZE as i32).contains(&y) {
+			Some(Self::new(x as u8, y as u8))
+		} else {
+			None
 		}
+	}
 
-		Self {
-			x: x as u8,
-			y: y as u8,
-		}
+	/// Wraps both axes around the chunk, so -1 becomes the last row or column.
+	pub fn new_overlooping(x: i8, y: i8) -> ChunkSubPos {
+		Self::new(
+			(x as i32).rem_euclid(CHUNK_SIZE as i32) as u8,
+			(y as i32).rem_euclid(CHUNK_SIZE as i32) as u8,
+		)
+	}
+
+	/// Every position of a chunk, row by row starting at the bottom left.
+	pub fn iter() -> impl Iterator<Item = ChunkSubPos> {
+		(0..CHUNK_SIZE as u8).flat_map(|y| (0..CHUNK_SIZE as u8).map(move |x| ChunkSubPos::new(x, y)))
 	}
 
 	pub fn is_border(&self, direction: Direction) -> bool {
@@ -135,15 +220,130 @@ impl ChunkSubPos {
 							  (self.y as i32 + direction.get_y_difference() as i32) as i8)
 	}
 
+	/// The neighbor in `direction`, if it is inside of the same chunk.
 	pub fn shift(&self, direction: Direction) -> Option<ChunkSubPos> {
-		let next_x = self.x as i32 + direction.get_x_difference() as i32;
-		let next_y = self.y as i32 + direction.get_y_difference() as i32;
-		if next_y < 0 || next_y > CHUNK_SIZE as i32 || next_x < 0 || next_x > CHUNK_SIZE as i32 {
+		Self::checked_new(self.x as i32 + direction.get_x_difference() as i32, self.y as i32 + direction.get_y_difference() as i32)
+	}
+}
+
+impl FloatPos {
+	pub fn new(x: f32, y: f32) -> FloatPos {
+		Self { x, y }
+	}
+
+	/// The tile this position is in, if that tile is inside of the world.
+	pub fn get_tile_pos(&self) -> Option<WorldPos> {
+		if !self.x.is_finite() || !self.y.is_finite() {
 			return None;
 		}
-		let mut pos = *self;
-		pos.x = next_x as u8;
-		pos.y = next_y as u8;
-		Some(pos)
+		WorldPos::checked_new(self.x.floor() as i64, self.y.floor() as i64)
+	}
+
+	/// The chunk this position is in. Positions outside of the world give the closest chunk.
+	pub fn get_chunk_pos(&self) -> ChunkPos {
+		let chunk_x = (self.x / CHUNK_SIZE as f32).floor() as i16;
+		let chunk_y = (self.y / CHUNK_SIZE as f32).floor() as u16;
+		ChunkPos::new(chunk_x, chunk_y)
+	}
+
+	pub fn offset(&self, x: f32, y: f32) -> FloatPos {
+		FloatPos::new(self.x + x, self.y + y)
+	}
+
+	pub fn get_distance(&self, other: &FloatPos) -> f32 {
+		((other.x - self.x).powi(2) + (other.y - self.y).powi(2)).sqrt()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::misc::pos::{ChunkPos, ChunkSubPos, WorldPos};
+	use crate::misc::util::{CHUNK_SIZE, Direction};
+
+	#[test]
+	fn row_zero_cannot_shift_down() {
+		let pos = WorldPos::new(5, 0);
+		assert_eq!(pos.shift(Direction::Down), None);
+		assert_eq!(pos.shift(Direction::Top), Some(WorldPos::new(5, 1)));
+		assert_eq!(WorldPos::new(5, WorldPos::MAX_Y).shift(Direction::Top), None);
+		assert_eq!(WorldPos::new(WorldPos::MIN_X, 3).shift(Direction::Left), None);
+		assert_eq!(WorldPos::new(WorldPos::MAX_X, 3).shift(Direction::Right), None);
+	}
+
+	#[test]
+	fn shifting_crosses_chunk_borders() {
+		let size = CHUNK_SIZE as i32;
+		for (x, y) in [(size - 1, 3), (size, 3), (-1, 3), (0, size as u32 - 1), (0, size as u32)] {
+			let pos = WorldPos::new(x, y);
+			for direction in Direction::iter() {
+				let expected = WorldPos::checked_new(x as i64 + direction.get_x_difference() as i64, y as i64 + direction.get_y_difference() as i64);
+				assert_eq!(pos.shift(direction), expected);
+			}
+		}
+
+		assert_eq!(WorldPos::new(size - 1, 0).get_chunk_pos(), &ChunkPos::new(0, 0));
+		assert_eq!(WorldPos::new(size, 0).get_chunk_pos(), &ChunkPos::new(1, 0));
+		assert_eq!(WorldPos::new(-1, 0).get_chunk_pos(), &ChunkPos::new(-1, 0));
+		assert_eq!(WorldPos::new(-1, 0).get_chunk_sub_pos(), &ChunkSubPos::new(CHUNK_SIZE as u8 - 1, 0));
+	}
+
+	#[test]
+	fn world_edges_survive_a_round_trip() {
+		for (x, y) in [(WorldPos::MIN_X, WorldPos::MIN_Y), (WorldPos::MAX_X, WorldPos::MAX_Y), (-1, 0), (0, 0)] {
+			let pos = WorldPos::checked_new(x as i64, y as i64).unwrap();
+			assert_eq!((pos.get_x(), pos.get_y()), (x, y));
+			assert_eq!(WorldPos::from_chunk(pos.get_chunk_pos(), pos.get_chunk_sub_pos().x, pos.get_chunk_sub_pos().y), pos);
+		}
+		assert_eq!(WorldPos::new(WorldPos::MIN_X, 0).get_chunk_pos(), &ChunkPos::new(i16::MIN, 0));
+		assert_eq!(WorldPos::new(WorldPos::MAX_X, WorldPos::MAX_Y).get_chunk_pos(), &ChunkPos::new(i16::MAX, u16::MAX));
+
+		assert_eq!(WorldPos::checked_new(WorldPos::MIN_X as i64 - 1, 0), None);
+		assert_eq!(WorldPos::checked_new(WorldPos::MAX_X as i64 + 1, 0), None);
+		assert_eq!(WorldPos::checked_new(0, -1), None);
+		assert_eq!(WorldPos::checked_new(0, WorldPos::MAX_Y as i64 + 1), None);
+		assert_eq!(WorldPos::checked_new(i64::MIN, i64::MAX), None);
+	}
+
+	#[test]
+	fn wrapping_new_wraps_around_the_edges() {
+		assert_eq!(WorldPos::wrapping_new(WorldPos::MAX_X as i64 + 1, 0), WorldPos::new(WorldPos::MIN_X, 0));
+		assert_eq!(WorldPos::wrapping_new(WorldPos::MIN_X as i64 - 1, 0), WorldPos::new(WorldPos::MAX_X, 0));
+		assert_eq!(WorldPos::wrapping_new(0, -1), WorldPos::new(0, WorldPos::MAX_Y));
+		assert_eq!(WorldPos::wrapping_new(0, WorldPos::MAX_Y as i64 + 1), WorldPos::new(0, 0));
+		assert_eq!(WorldPos::wrapping_new(12, 34), WorldPos::new(12, 34));
+
+		// Any input lands inside of the world.
+		for value in [i64::MIN, i64::MIN / 3, -1, 0, 1, i64::MAX / 7, i64::MAX] {
+			let pos = WorldPos::wrapping_new(value, value);
+			assert!(WorldPos::checked_new(pos.get_x() as i64, pos.get_y() as i64).is_some());
+		}
+	}
+
+	#[test]
+	fn checked_add_stops_at_the_edges() {
+		let max = WorldPos::new(WorldPos::MAX_X, WorldPos::MAX_Y);
+		assert_eq!(max.checked_add(1, 0), None);
+		assert_eq!(max.checked_add(0, 1), None);
+		assert_eq!(max.checked_add(-1, -1), Some(WorldPos::new(WorldPos::MAX_X - 1, WorldPos::MAX_Y - 1)));
+
+		let min = WorldPos::new(WorldPos::MIN_X, WorldPos::MIN_Y);
+		assert_eq!(min.checked_add(-1, 0), None);
+		assert_eq!(min.checked_add(0, -1), None);
+		assert_eq!(min.checked_add(i32::MAX, i32::MAX), None);
+		assert_eq!(min.wrapping_add(-1, -1), max);
+	}
+
+	#[test]
+	fn chunk_sub_pos_iter_visits_every_position_once() {
+		let positions: Vec<ChunkSubPos> = ChunkSubPos::iter().collect();
+		assert_eq!(positions.len(), CHUNK_SIZE * CHUNK_SIZE);
+		assert_eq!(positions.first(), Some(&ChunkSubPos::new(0, 0)));
+		assert_eq!(positions[1], ChunkSubPos::new(1, 0));
+		assert_eq!(positions.last(), Some(&ChunkSubPos::new(CHUNK_SIZE as u8 - 1, CHUNK_SIZE as u8 - 1)));
+		for (index, pos) in positions.iter().enumerate() {
+			assert_eq!((pos.y as usize * CHUNK_SIZE + pos.x as usize), index);
+		}
+		assert_eq!(ChunkSubPos::checked_new(CHUNK_SIZE as i32, 0), None);
+		assert_eq!(ChunkSubPos::checked_new(0, -1), None);
 	}
 }
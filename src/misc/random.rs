@@ -0,0 +1,29 @@
+/// A small seeded xorshift generator. Same seed, same numbers, on every platform.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Random {
+	state: u64,
+}
+
+impl Random {
+	pub fn new(seed: u64) -> Random {
+		// xorshift gets stuck on 0
+		Self { state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1 }
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state ^= self.state << 13;
+		self.state ^= self.state >> 7;
+		self.state ^= self.state << 17;
+		self.state
+	}
+
+	/// A number in `0..bound`.
+	pub fn next_range(&mut self, bound: u32) -> u32 {
+		(self.next_u64() % bound as u64) as u32
+	}
+
+	/// True once every `chance` calls on average.
+	pub fn one_in(&mut self, chance: u32) -> bool {
+		self.next_range(chance) == 0
+	}
+}
@@ -9,13 +9,15 @@ pub enum CallbackResponse {
 	Stop,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum CollisionType {
 	CollidesPlayer,
+	/// Only solid from above, and only for things that do not want to drop through.
+	Platform,
 	Nothing,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum Direction {
 	Top,
 	Down,
@@ -1,11 +1,62 @@
-const BASE_SPEED: f32 = 32f32;
+use std::io::{Read, Result, Write};
 
+use crate::item::{COPPER_PICKAXE, ItemKind};
+use crate::item::inventory::{HOTBAR_SLOTS, Inventory, PLAYER_SLOTS};
+use crate::item::stack::ItemStack;
+use crate::misc::pos::{FloatPos, WorldPos};
+use crate::world::physics::{Aabb, GRAVITY, TERMINAL_VELOCITY};
+use crate::world::save::{invalid_data, Persistent};
+use crate::world::World;
+
+/// Bump this every time the layout of a player file changes.
+const PLAYER_VERSION: u8 = 1;
+
+/// Top walking speed in tiles per tick.
+const BASE_SPEED: f32 = 0.1875;
+/// How fast the player speeds up while walking, in tiles per tick.
+const ACCELERATION: f32 = 0.015;
+/// How fast the player slows down without input, on the ground and in the air.
+const GROUND_FRICTION: f32 = 0.025;
+const AIR_FRICTION: f32 = 0.008;
+/// Upwards speed kept while the jump key is held, in tiles per tick.
+pub const JUMP_SPEED: f32 = 0.32;
+/// For how many ticks holding the jump key keeps the player going up.
+const JUMP_TICKS: u32 = 15;
+/// For how many ticks after walking off a ledge the player can still jump.
+const COYOTE_TICKS: u32 = 6;
+/// Speed while flying, in tiles per tick.
+const FLY_SPEED: f32 = 1.0;
+pub const PLAYER_WIDTH: f32 = 1.25;
+pub const PLAYER_HEIGHT: f32 = 2.625;
+/// How far the player can mine and place, from the center of the player to the center of the tile.
+pub const REACH: f32 = 6.0;
+/// Ticks between two placed blocks.
+pub const BLOCK_USE_TIME: u32 = 15;
+
+/// `pos_x` and `pos_y` are the bottom center of the player in tiles.
 pub struct Player {
 	pub speed: f32,
 	pub pos_x: f32,
 	pub pos_y: f32,
 	pub vel_x: f32,
 	pub vel_y: f32,
+	pub on_ground: bool,
+	/// Which way the player wants to go, between -1 and 1 on both axes. Holding down drops through platforms.
+	pub input_x: f32,
+	pub input_y: f32,
+	pub jump_held: bool,
+	/// Debug movement that ignores gravity and collision.
+	pub flying: bool,
+	/// The tile the player is pointing at and if the mine or place button is held.
+	pub target: Option<WorldPos>,
+	pub mining: bool,
+	pub placing: bool,
+	pub inventory: Inventory,
+	/// The hotbar slot of the item in use.
+	pub selected_slot: usize,
+	jump_ticks: u32,
+	coyote_ticks: u32,
+	use_cooldown: u32,
 }
 
 impl Player {
@@ -16,10 +67,326 @@ impl Player {
 			pos_y: 0.0,
 			vel_x: 0.0,
 			vel_y: 0.0,
+			on_ground: false,
+			input_x: 0.0,
+			input_y: 0.0,
+			jump_held: false,
+			flying: false,
+			target: None,
+			mining: false,
+			placing: false,
+			inventory: Self::create_inventory(),
+			selected_slot: 0,
+			jump_ticks: 0,
+			coyote_ticks: 0,
+			use_cooldown: 0,
+		}
+	}
+
+	pub fn get_aabb(&self) -> Aabb {
+		Aabb::new(self.pos_x - PLAYER_WIDTH / 2.0, self.pos_y, PLAYER_WIDTH, PLAYER_HEIGHT)
+	}
+
+	/// The middle of the player, where reach gets measured from.
+	pub fn get_center(&self) -> FloatPos {
+		FloatPos::new(self.pos_x, self.pos_y + PLAYER_HEIGHT / 2.0)
+	}
+
+	pub fn can_reach(&self, pos: &WorldPos) -> bool {
+		self.get_center().get_distance(&pos.get_center()) <= REACH
+	}
+
+	/// Every player starts out with a pickaxe.
+	fn create_inventory() -> Inventory {
+		let mut inventory = Inventory::new(PLAYER_SLOTS);
+		inventory.set(0, Some(ItemStack::new(COPPER_PICKAXE, 1)));
+		inventory
+	}
+
+	pub fn get_held_item(&self) -> Option<&ItemStack> {
+		self.inventory.get(self.selected_slot)
+	}
+
+	/// The power and use time of the held item, if it is a tool.
+	pub fn get_tool(&self) -> Option<(u32, u32)> {
+		match self.get_held_item()?.get_type()?.kind {
+			ItemKind::Tool { power, use_time } => Some((power, use_time)),
+			_ => None,
+		}
+	}
+
+	/// Selects a hotbar slot. Slots outside of the hotbar are ignored.
+	pub fn select_slot(&mut self, slot: usize) {
+		if slot < HOTBAR_SLOTS {
+			self.selected_slot = slot;
+		}
+	}
+
+	/// Starts using the held item if the last use is over. The next use can start in `use_time` ticks.
+	pub fn try_use(&mut self, use_time: u32) -> bool {
+		if self.use_cooldown > 0 {
+			return false;
+		}
+		self.use_cooldown = use_time;
+		true
+	}
+
+	/// Applies the input, gravity and friction and moves the player through the world.
+	pub fn tick(&mut self, world: &World) {
+		self.use_cooldown = self.use_cooldown.saturating_sub(1);
+		if self.flying {
+			self.vel_x = 0.0;
+			self.vel_y = 0.0;
+			self.on_ground = false;
+			self.pos_x += self.input_x * FLY_SPEED;
+			self.pos_y += self.input_y * FLY_SPEED;
+			return;
+		}
+
+		self.tick_walk();
+		self.tick_jump();
+		if self.jump_ticks == 0 {
+			self.vel_y = (self.vel_y - GRAVITY).max(-TERMINAL_VELOCITY);
+		}
+
+		let mut aabb = self.get_aabb();
+		let movement = world.move_aabb(&mut aabb, self.vel_x, self.vel_y, self.on_ground, self.input_y < 0.0);
+		if movement.hit_x {
+			self.vel_x = 0.0;
+		}
+		if movement.hit_top {
+			self.jump_ticks = 0;
+		}
+		if movement.on_ground || movement.hit_top {
+			self.vel_y = 0.0;
+		}
+		self.on_ground = movement.on_ground;
+		if self.on_ground {
+			self.coyote_ticks = COYOTE_TICKS;
+		} else {
+			self.coyote_ticks = self.coyote_ticks.saturating_sub(1);
+		}
+		self.pos_x = aabb.x + PLAYER_WIDTH / 2.0;
+		self.pos_y = aabb.y;
+	}
+
+	/// Speeds up towards the input direction and slows down through friction when there is none or it points the other way.
+	fn tick_walk(&mut self) {
+		let target = self.input_x.clamp(-1.0, 1.0) * self.speed;
+		let friction = if self.on_ground { GROUND_FRICTION } else { AIR_FRICTION };
+		if target != 0.0 && self.vel_x.abs() <= target.abs() && self.vel_x * target >= 0.0 {
+			self.vel_x = approach(self.vel_x, target, ACCELERATION);
+		} else if target != 0.0 && self.vel_x * target < 0.0 {
+			self.vel_x = approach(self.vel_x, target, ACCELERATION + friction);
+		} else {
+			self.vel_x = approach(self.vel_x, target, friction);
+		}
+	}
+
+	/// Starts a jump when possible and keeps it going while the key is held.
+	fn tick_jump(&mut self) {
+		if !self.jump_held {
+			self.jump_ticks = 0;
+		} else if self.jump_ticks == 0 && self.vel_y <= 0.0 && (self.on_ground || self.coyote_ticks > 0) {
+			self.jump_ticks = JUMP_TICKS;
+			self.coyote_ticks = 0;
+		}
+
+		if self.jump_ticks > 0 {
+			self.vel_y = JUMP_SPEED;
+			self.jump_ticks -= 1;
+		}
+	}
+}
+
+// Only what outlives a session gets saved. Velocity, input and cooldowns start over.
+impl Persistent for Player {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		PLAYER_VERSION.write(out)?;
+		self.pos_x.write(out)?;
+		self.pos_y.write(out)?;
+		(self.flying as u8).write(out)?;
+		(self.selected_slot as u8).write(out)?;
+		self.inventory.write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let version = u8::read(input)?;
+		if version != PLAYER_VERSION {
+			return Err(invalid_data(format!("Unsupported player version {}", version)));
 		}
+
+		let mut player = Player::new();
+		player.pos_x = f32::read(input)?;
+		player.pos_y = f32::read(input)?;
+		if !player.pos_x.is_finite() || !player.pos_y.is_finite() {
+			return Err(invalid_data(format!("Player position {} {} is not finite", player.pos_x, player.pos_y)));
+		}
+		player.flying = u8::read(input)? != 0;
+		player.selected_slot = u8::read(input)? as usize;
+		if player.selected_slot >= HOTBAR_SLOTS {
+			return Err(invalid_data(format!("Selected slot {} is outside of the hotbar", player.selected_slot)));
+		}
+		player.inventory = Inventory::read(input)?;
+		if player.inventory.len() != PLAYER_SLOTS {
+			return Err(invalid_data(format!("Player inventory has {} slots instead of {}", player.inventory.len(), PLAYER_SLOTS)));
+		}
+		Ok(player)
 	}
+}
 
-	pub fn tick(&mut self) {
+/// Moves `value` towards `target` by at most `amount`.
+fn approach(value: f32, target: f32, amount: f32) -> f32 {
+	if value < target {
+		(value + amount).min(target)
+	} else {
+		(value - amount).max(target)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::ErrorKind;
+
+	use crate::item::IRON_BAR;
+	use crate::item::stack::ItemStack;
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::player::{ACCELERATION, BASE_SPEED, COYOTE_TICKS, GROUND_FRICTION, JUMP_SPEED, Player, PLAYER_VERSION};
+	use crate::world::save::Persistent;
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile::{self, Tile};
+	use crate::world::World;
+
+	/// Three chunks side by side with a stone floor up to y 4, and a player standing on it at x 24.5.
+	fn create_standing_player() -> (World, Player) {
+		let floor = |_, y| if y < 5 { tile::STONE } else { tile::AIR };
+		let world = create_world((-1..=1).map(|x| (ChunkPos::new(x, 0), create_chunk(floor))).collect());
+		let mut player = Player::new();
+		player.pos_x = 24.5;
+		player.pos_y = 5.0;
+		player.tick(&world);
+		assert!(player.on_ground);
+		(world, player)
+	}
 
+	/// Ticks until the player is back on the ground and returns the highest the player got.
+	fn get_jump_apex(world: &World, player: &mut Player, held_ticks: u32) -> f32 {
+		player.jump_held = true;
+		let mut apex = player.pos_y;
+		for tick in 0..200 {
+			player.jump_held = tick < held_ticks;
+			player.tick(world);
+			apex = apex.max(player.pos_y);
+			if tick > 0 && player.on_ground {
+				return apex;
+			}
+		}
+		panic!("The player never landed");
+	}
+
+	#[test]
+	fn player_survives_a_round_trip() {
+		let mut player = Player::new();
+		player.pos_x = -12.5;
+		player.pos_y = 340.0;
+		player.vel_x = 1.0;
+		player.selected_slot = 3;
+		player.inventory.insert(ItemStack::new(IRON_BAR, 12));
+		let mut bytes = Vec::new();
+		player.write(&mut bytes).unwrap();
+
+		let loaded = Player::read(&mut bytes.as_slice()).unwrap();
+		assert_eq!((loaded.pos_x, loaded.pos_y), (-12.5, 340.0));
+		assert_eq!(loaded.vel_x, 0.0);
+		assert_eq!(loaded.selected_slot, 3);
+		assert_eq!(loaded.inventory, player.inventory);
+	}
+
+	#[test]
+	fn invalid_players_are_rejected() {
+		let mut bytes = Vec::new();
+		Player::new().write(&mut bytes).unwrap();
+
+		let mut wrong_version = bytes.clone();
+		wrong_version[0] = PLAYER_VERSION + 1;
+		let mut wrong_slot = bytes.clone();
+		wrong_slot[10] = 200;
+		let mut infinite = bytes.clone();
+		infinite[1..5].copy_from_slice(&f32::INFINITY.to_le_bytes());
+		for bytes in [wrong_version, wrong_slot, infinite] {
+			let result = Player::read(&mut bytes.as_slice());
+			assert!(matches!(result, Err(error) if error.kind() == ErrorKind::InvalidData));
+		}
+
+		let result = Player::read(&mut &bytes[..bytes.len() - 1]);
+		assert!(matches!(result, Err(error) if error.kind() == ErrorKind::UnexpectedEof));
+	}
+
+	#[test]
+	fn walking_reaches_base_speed_without_overshooting() {
+		let (world, mut player) = create_standing_player();
+		player.input_x = 1.0;
+		let ticks = (BASE_SPEED / ACCELERATION).ceil() as u32;
+		for _ in 0..ticks - 1 {
+			player.tick(&world);
+			assert!(player.vel_x > 0.0 && player.vel_x < BASE_SPEED);
+		}
+		player.tick(&world);
+		assert_eq!(player.vel_x, BASE_SPEED);
+
+		for _ in 0..20 {
+			player.tick(&world);
+			assert_eq!(player.vel_x, BASE_SPEED);
+		}
+	}
+
+	#[test]
+	fn turning_around_brakes_with_acceleration_and_friction() {
+		let (world, mut player) = create_standing_player();
+		player.input_x = 1.0;
+		for _ in 0..20 {
+			player.tick(&world);
+		}
+
+		player.input_x = -1.0;
+		player.tick(&world);
+		assert!((player.vel_x - (BASE_SPEED - ACCELERATION - GROUND_FRICTION)).abs() < 0.0001);
+
+		// Without input only friction slows the player down.
+		player.input_x = 0.0;
+		let speed = player.vel_x;
+		player.tick(&world);
+		assert!((player.vel_x - (speed - GROUND_FRICTION)).abs() < 0.0001);
+	}
+
+	#[test]
+	fn releasing_jump_early_gives_a_lower_apex() {
+		let (world, mut player) = create_standing_player();
+		let full = get_jump_apex(&world, &mut player, 100);
+		let short = get_jump_apex(&world, &mut player, 3);
+		assert!(short > 5.0);
+		assert!(full > short + 1.0, "Holding jumped to {} and releasing to {}", full, short);
+		assert_eq!(player.pos_y, 5.0);
+	}
+
+	#[test]
+	fn coyote_jumps_only_work_right_after_leaving_the_ground() {
+		for (air_ticks, jumps) in [(COYOTE_TICKS - 1, true), (COYOTE_TICKS, false)] {
+			let (mut world, mut player) = create_standing_player();
+			// Takes the floor away under the player.
+			for x in 22..=26 {
+				for y in 1..5 {
+					world.set(&WorldPos::new(x, y), Tile::air());
+				}
+			}
+			for _ in 0..air_ticks {
+				player.tick(&world);
+				assert!(!player.on_ground);
+			}
+
+			player.jump_held = true;
+			player.tick(&world);
+			assert_eq!(player.vel_y == JUMP_SPEED, jumps, "Jump after {} ticks in the air", air_ticks);
+		}
 	}
 }
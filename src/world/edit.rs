@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use crate::misc::pos::{ChunkSubPos, WorldPos};
+use crate::world::Layer;
+use crate::world::liquid::Liquid;
+use crate::world::tile::Tile;
+use crate::world::wall::Wall;
+
+/// A batch of changes that gets applied to the world at once with `World::apply_edit`.
+/// Neighbors only get recalculated once for the whole batch instead of once per change.
+#[derive(Default)]
+pub struct WorldEdit {
+	pub(crate) tiles: HashMap<WorldPos, Tile>,
+	pub(crate) walls: HashMap<WorldPos, Wall>,
+	pub(crate) liquids: HashMap<WorldPos, Liquid>,
+}
+
+impl WorldEdit {
+	pub fn new() -> WorldEdit {
+		Self::default()
+	}
+
+	/// Queues a change. Setting the same position twice keeps the last value.
+	pub fn set<C: Layer>(&mut self, pos: &WorldPos, object: C) {
+		C::get_edits_mut(self).insert(*pos, object);
+	}
+
+	pub fn get<C: Layer>(&self, pos: &WorldPos) -> Option<&C> {
+		C::get_edits(self).get(pos)
+	}
+
+	pub fn len(&self) -> usize {
+		self.tiles.len() + self.walls.len() + self.liquids.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+/// The changes waiting for a chunk that is not loaded, keyed by their position and layer so every position
+/// only keeps its latest change per layer.
+pub type PendingEdits = HashMap<(ChunkSubPos, u8), PendingEdit>;
+
+/// A change to a chunk that was not loaded yet. Gets applied once the chunk gets generated or loaded.
+#[derive(Copy, Clone)]
+pub enum PendingEdit {
+	Tile(ChunkSubPos, Tile),
+	Wall(ChunkSubPos, Wall),
+	Liquid(ChunkSubPos, Liquid),
+}
+
+impl PendingEdit {
+	pub fn get_pos(&self) -> &ChunkSubPos {
+		match self {
+			PendingEdit::Tile(pos, _) | PendingEdit::Wall(pos, _) | PendingEdit::Liquid(pos, _) => pos,
+		}
+	}
+
+	/// The layer the edit changes, which is also how it gets saved.
+	pub fn get_layer(&self) -> u8 {
+		match self {
+			PendingEdit::Tile(..) => 0,
+			PendingEdit::Wall(..) => 1,
+			PendingEdit::Liquid(..) => 2,
+		}
+	}
+
+	pub fn get_key(&self) -> (ChunkSubPos, u8) {
+		(*self.get_pos(), self.get_layer())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::world::edit::PendingEdit;
+	use crate::world::testing::create_world;
+	use crate::world::tile;
+	use crate::world::tile::Tile;
+	use crate::world::wall::Wall;
+
+	#[test]
+	fn pending_edits_keep_the_latest_change_per_layer() {
+		let mut world = create_world(Vec::new());
+		let pos = WorldPos::new(5, 5);
+		world.set(&pos, Tile::id(tile::DIRT));
+		world.set(&pos, Tile::id(tile::STONE));
+		world.set(&pos, Wall::air());
+		world.set(&WorldPos::new(6, 5), Tile::id(tile::DIRT));
+
+		assert_eq!(world.get_pending_edit_count(), 3);
+		let tiles: Vec<Tile> = world.get_pending_edits(&ChunkPos::new(0, 0))
+			.filter_map(|edit| match edit {
+				PendingEdit::Tile(sub_pos, tile) if sub_pos == pos.get_chunk_sub_pos() => Some(*tile),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(tiles.len(), 1);
+		assert_eq!(tiles[0].id, tile::STONE);
+	}
+}
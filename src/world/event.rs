@@ -0,0 +1,163 @@
+use crossbeam::channel::{bounded, Receiver, Sender, TrySendError};
+
+use crate::misc::pos::{ChunkPos, WorldPos};
+use crate::world::liquid::Liquid;
+use crate::world::tile::Tile;
+use crate::world::wall::Wall;
+
+/// How many events wait for a subscriber before it starts missing them.
+pub const EVENT_CAPACITY: usize = 4096;
+
+/// Something that happened in the world that other systems might want to react to.
+#[derive(Copy, Clone)]
+pub enum WorldEvent {
+	TileChanged { pos: WorldPos, old: Tile, new: Tile },
+	WallChanged { pos: WorldPos, old: Wall, new: Wall },
+	LiquidChanged { pos: WorldPos, old: Liquid, new: Liquid },
+	ChunkLoaded(ChunkPos),
+	ChunkUnloaded(ChunkPos),
+	/// Many positions of the chunk changed at once through a `WorldEdit`, without an event for every single one of them.
+	/// Everything a subscriber knows about the chunk is outdated. Gets sent to every subscriber of tile, wall or liquid changes too.
+	ChunkEdited(ChunkPos),
+	/// The subscriber did not read its events in time and missed some of them, so everything it knows about the world is outdated.
+	/// Gets sent to every subscriber no matter which kinds it subscribed to.
+	Lagged,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum EventKind {
+	Tile,
+	Wall,
+	Liquid,
+	ChunkLoad,
+	ChunkUnload,
+	ChunkEdit,
+	/// Does not need to be subscribed to, see `WorldEvent::Lagged`.
+	Lag,
+}
+
+impl EventKind {
+	pub fn all() -> [EventKind; 6] {
+		[EventKind::Tile, EventKind::Wall, EventKind::Liquid, EventKind::ChunkLoad, EventKind::ChunkUnload, EventKind::ChunkEdit]
+	}
+}
+
+impl WorldEvent {
+	pub fn get_kind(&self) -> EventKind {
+		match self {
+			WorldEvent::TileChanged { .. } => EventKind::Tile,
+			WorldEvent::WallChanged { .. } => EventKind::Wall,
+			WorldEvent::LiquidChanged { .. } => EventKind::Liquid,
+			WorldEvent::ChunkLoaded(_) => EventKind::ChunkLoad,
+			WorldEvent::ChunkUnloaded(_) => EventKind::ChunkUnload,
+			WorldEvent::ChunkEdited(_) => EventKind::ChunkEdit,
+			WorldEvent::Lagged => EventKind::Lag,
+		}
+	}
+
+	/// The position that changed, if the event is about a single position.
+	pub fn get_pos(&self) -> Option<&WorldPos> {
+		match self {
+			WorldEvent::TileChanged { pos, .. } | WorldEvent::WallChanged { pos, .. } | WorldEvent::LiquidChanged { pos, .. } => Some(pos),
+			WorldEvent::ChunkLoaded(_) | WorldEvent::ChunkUnloaded(_) | WorldEvent::ChunkEdited(_) | WorldEvent::Lagged => None,
+		}
+	}
+}
+
+/// Hands out a channel of `EVENT_CAPACITY` events to every subscriber. Subscribers that dropped their receiver get removed on the next event.
+/// A full channel drops its events and gets a `WorldEvent::Lagged` once it has room again.
+#[derive(Default)]
+pub struct EventBus {
+	subscribers: Vec<Subscriber>,
+}
+
+struct Subscriber {
+	kinds: Vec<EventKind>,
+	sender: Sender<WorldEvent>,
+	lagged: bool,
+}
+
+impl Subscriber {
+	fn wants(&self, kind: EventKind) -> bool {
+		self.kinds.contains(&kind) || (kind == EventKind::ChunkEdit && self.kinds.iter().any(|kind| matches!(kind, EventKind::Tile | EventKind::Wall | EventKind::Liquid)))
+	}
+
+	/// Returns false if the receiver is gone.
+	fn send(&mut self, event: WorldEvent) -> bool {
+		if self.lagged {
+			match self.sender.try_send(WorldEvent::Lagged) {
+				Ok(()) => self.lagged = false,
+				Err(TrySendError::Full(_)) => return true,
+				Err(TrySendError::Disconnected(_)) => return false,
+			}
+		}
+
+		match self.sender.try_send(event) {
+			Ok(()) => true,
+			Err(TrySendError::Full(_)) => {
+				self.lagged = true;
+				true
+			}
+			Err(TrySendError::Disconnected(_)) => false,
+		}
+	}
+}
+
+impl EventBus {
+	pub fn subscribe(&mut self, kinds: &[EventKind]) -> Receiver<WorldEvent> {
+		let (sender, receiver) = bounded(EVENT_CAPACITY);
+		self.subscribers.push(Subscriber { kinds: kinds.to_vec(), sender, lagged: false });
+		receiver
+	}
+
+	pub fn emit(&mut self, event: WorldEvent) {
+		let kind = event.get_kind();
+		self.subscribers.retain_mut(|subscriber| {
+			!subscriber.wants(kind) || subscriber.send(event)
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn create_event(x: i32) -> WorldEvent {
+		WorldEvent::TileChanged { pos: WorldPos::new(x, 0), old: Tile::air(), new: Tile::air() }
+	}
+
+	#[test]
+	fn full_subscriber_gets_told_it_lagged() {
+		let mut bus = EventBus::default();
+		let receiver = bus.subscribe(&[EventKind::Tile]);
+		for x in 0..EVENT_CAPACITY as i32 + 10 {
+			bus.emit(create_event(x));
+		}
+		assert_eq!(receiver.len(), EVENT_CAPACITY);
+
+		receiver.try_iter().for_each(drop);
+		bus.emit(create_event(0));
+		let events: Vec<EventKind> = receiver.try_iter().map(|event| event.get_kind()).collect();
+		assert_eq!(events, vec![EventKind::Lag, EventKind::Tile]);
+	}
+
+	#[test]
+	fn chunk_edits_reach_change_subscribers() {
+		let mut bus = EventBus::default();
+		let walls = bus.subscribe(&[EventKind::Wall]);
+		let loads = bus.subscribe(&[EventKind::ChunkLoad]);
+		bus.emit(WorldEvent::ChunkEdited(ChunkPos::new(0, 0)));
+		assert_eq!(walls.len(), 1);
+		assert!(loads.is_empty());
+	}
+
+	#[test]
+	fn dropped_receivers_get_removed() {
+		let mut bus = EventBus::default();
+		let kept = bus.subscribe(&[EventKind::Tile]);
+		drop(bus.subscribe(&[EventKind::Tile]));
+		bus.emit(create_event(0));
+		assert_eq!(bus.subscribers.len(), 1);
+		assert_eq!(kept.len(), 1);
+	}
+}
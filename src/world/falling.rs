@@ -0,0 +1,149 @@
+use crate::misc::pos::WorldPos;
+use crate::world::tile;
+use crate::world::tile::Tile;
+use crate::world::World;
+
+const FALL_ACCELERATION: f32 = 0.05;
+const MAX_FALL_SPEED: f32 = 0.8;
+
+/// A tile that lost its footing and is falling through the world.
+#[derive(Copy, Clone)]
+pub struct FallingTile {
+	pub tile: Tile,
+	pub x: i32,
+	/// The bottom of the tile in tiles.
+	pub y: f32,
+	pub vel_y: f32,
+	/// The history action that made the tile fall, its landing gets recorded there too.
+	pub action: Option<u64>,
+}
+
+pub enum FallResult {
+	Falling,
+	/// The tile hit something and should become a tile again at this height.
+	Landed(u32),
+}
+
+impl FallingTile {
+	pub fn new(tile: Tile, pos: &WorldPos) -> FallingTile {
+		Self {
+			tile: Tile { matrix: Default::default(), ..tile },
+			x: pos.get_x(),
+			y: pos.get_y() as f32,
+			vel_y: 0.0,
+			action: None,
+		}
+	}
+
+	pub fn tick(&mut self, world: &World) -> FallResult {
+		self.vel_y = (self.vel_y + FALL_ACCELERATION).min(MAX_FALL_SPEED);
+
+		let mut remaining = self.vel_y;
+		loop {
+			let cell_y = self.y.floor() as i64;
+			let to_floor = self.y - cell_y as f32;
+			if remaining <= to_floor {
+				self.y -= remaining;
+				return FallResult::Falling;
+			}
+
+			// We reached the bottom of our cell, check what is below.
+			self.y = cell_y as f32;
+			remaining -= to_floor;
+			if cell_y <= 0 {
+				return FallResult::Landed(0);
+			}
+
+			match world.get::<Tile>(&WorldPos::new(self.x, (cell_y - 1) as u32)) {
+				// Chunk is not loaded yet, wait for it.
+				None => {
+					self.vel_y = 0.0;
+					return FallResult::Falling;
+				}
+				Some(below) if below.id != tile::AIR => return FallResult::Landed(cell_y as u32),
+				Some(_) => {
+					let step = remaining.min(1.0);
+					self.y -= step;
+					remaining -= step;
+					if remaining <= 0.0 {
+						return FallResult::Falling;
+					}
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::world::falling::FallingTile;
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile::{Tile, TileId};
+	use crate::world::{tile, World};
+
+	fn tick_until_landed(world: &mut World) {
+		for _ in 0..1000 {
+			if world.get_falling_tiles().is_empty() {
+				return;
+			}
+			world.tick().unwrap();
+		}
+		panic!("Tiles are still falling");
+	}
+
+	fn get_id(world: &World, x: i32, y: u32) -> TileId {
+		world.get::<Tile>(&WorldPos::new(x, y)).unwrap().id
+	}
+
+	#[test]
+	fn column_falls_together() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| match (x, y) {
+			(_, 0) | (5, 1) => tile::STONE,
+			(5, 2..=5) => tile::SAND,
+			_ => tile::AIR,
+		}))]);
+
+		world.set(&WorldPos::new(5, 1), Tile::air());
+		assert_eq!(world.get_falling_tiles().len(), 4);
+		tick_until_landed(&mut world);
+
+		for y in 1..=4 {
+			assert_eq!(get_id(&world, 5, y), tile::SAND);
+		}
+		assert_eq!(get_id(&world, 5, 5), tile::AIR);
+	}
+
+	#[test]
+	fn falls_into_the_chunk_below() {
+		let mut world = create_world(vec![
+			(ChunkPos::new(0, 0), create_chunk(|_, y| if y == 0 { tile::STONE } else { tile::AIR })),
+			(ChunkPos::new(0, 1), create_chunk(|x, y| match (x, y) {
+				(5, 5) => tile::STONE,
+				(5, 6) => tile::SAND,
+				_ => tile::AIR,
+			})),
+		]);
+
+		world.set(&WorldPos::new(5, 29), Tile::air());
+		assert_eq!(world.get_falling_tiles().len(), 1);
+		tick_until_landed(&mut world);
+
+		assert_eq!(get_id(&world, 5, 1), tile::SAND);
+		assert_eq!(get_id(&world, 5, 30), tile::AIR);
+	}
+
+	#[test]
+	fn landing_in_an_unloaded_chunk_defers_the_tile() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| if x == 5 && y < 23 { tile::STONE } else { tile::AIR }))]);
+
+		// Both land on the top tile of the chunk at once, so the second one stacks into the unloaded chunk above.
+		let falling = FallingTile::new(Tile::id(tile::SAND), &WorldPos::new(5, 23));
+		world.falling_tiles.push(falling);
+		world.falling_tiles.push(falling);
+		tick_until_landed(&mut world);
+
+		assert_eq!(get_id(&world, 5, 23), tile::SAND);
+		assert_eq!(world.get_pending_edits(&ChunkPos::new(0, 1)).count(), 1);
+	}
+}
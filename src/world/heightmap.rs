@@ -0,0 +1,191 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::misc::pos::{ChunkPos, ChunkSubPos, WorldPos};
+use crate::misc::util::{CHUNK_SIZE, CollisionType};
+use crate::world::{Chunk, Grid, wall};
+use crate::world::tile::Tile;
+use crate::world::wall::Wall;
+
+/// The highest solid tile and the highest wall of a tile column.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct ColumnHeight {
+	pub tile: Option<u32>,
+	pub wall: Option<u32>,
+}
+
+/// Caches the column heights of every loaded tile column. Only loaded chunks are taken into account.
+#[derive(Default)]
+pub struct Heightmap {
+	columns: HashMap<i32, ColumnHeight>,
+	chunk_rows: HashMap<i16, BTreeSet<u16>>,
+}
+
+impl Heightmap {
+	/// The heights of column `x`, if any chunk of the column is loaded.
+	pub fn get(&self, x: i32) -> Option<&ColumnHeight> {
+		self.columns.get(&x)
+	}
+
+	/// Takes a newly inserted chunk into account.
+	pub(crate) fn add_chunk(&mut self, pos: &ChunkPos, chunks: &HashMap<ChunkPos, Chunk>) {
+		self.chunk_rows.entry(pos.x).or_default().insert(pos.y);
+		self.recalculate_chunk_column(pos.x, chunks);
+	}
+
+	pub(crate) fn remove_chunk(&mut self, pos: &ChunkPos, chunks: &HashMap<ChunkPos, Chunk>) {
+		if let Some(rows) = self.chunk_rows.get_mut(&pos.x) {
+			rows.remove(&pos.y);
+			if rows.is_empty() {
+				self.chunk_rows.remove(&pos.x);
+			}
+		}
+		self.recalculate_chunk_column(pos.x, chunks);
+	}
+
+	/// Updates the column of `pos` after the tile or wall there changed.
+	pub(crate) fn update(&mut self, pos: &WorldPos, chunks: &HashMap<ChunkPos, Chunk>) {
+		let chunk = match chunks.get(pos.get_chunk_pos()) {
+			Some(chunk) => chunk,
+			None => return,
+		};
+
+		let (tile, wall) = Self::get_solid(chunk, pos.get_chunk_sub_pos());
+		let height = self.columns.entry(pos.get_x()).or_default();
+		let y = pos.get_y();
+		if (!tile && height.tile == Some(y)) || (!wall && height.wall == Some(y)) {
+			self.recalculate(pos.get_x(), chunks);
+			return;
+		}
+
+		if tile && height.tile.is_none_or(|height| y > height) {
+			height.tile = Some(y);
+		}
+		if wall && height.wall.is_none_or(|height| y > height) {
+			height.wall = Some(y);
+		}
+	}
+
+	fn recalculate_chunk_column(&mut self, chunk_x: i16, chunks: &HashMap<ChunkPos, Chunk>) {
+		for x in 0..CHUNK_SIZE as i32 {
+			self.recalculate(chunk_x as i32 * CHUNK_SIZE as i32 + x, chunks);
+		}
+	}
+
+	/// Scans the loaded chunks of the column from the top until both heights are found.
+	fn recalculate(&mut self, x: i32, chunks: &HashMap<ChunkPos, Chunk>) {
+		let bottom = WorldPos::new(x, 0);
+		let chunk_x = bottom.get_chunk_pos().x;
+		let sub_x = bottom.get_chunk_sub_pos().x;
+		let rows = match self.chunk_rows.get(&chunk_x) {
+			Some(rows) => rows,
+			None => {
+				self.columns.remove(&x);
+				return;
+			}
+		};
+
+		let mut height = ColumnHeight::default();
+		'rows: for chunk_y in rows.iter().rev() {
+			let chunk_pos = ChunkPos::new(chunk_x, *chunk_y);
+			if let Some(chunk) = chunks.get(&chunk_pos) {
+				for sub_y in (0..CHUNK_SIZE as u8).rev() {
+					let (tile, wall) = Self::get_solid(chunk, &ChunkSubPos::new(sub_x, sub_y));
+					let y = WorldPos::from_chunk(&chunk_pos, sub_x, sub_y).get_y();
+					if tile && height.tile.is_none() {
+						height.tile = Some(y);
+					}
+					if wall && height.wall.is_none() {
+						height.wall = Some(y);
+					}
+					if height.tile.is_some() && height.wall.is_some() {
+						break 'rows;
+					}
+				}
+			}
+		}
+		self.columns.insert(x, height);
+	}
+
+	/// If the tile collides and if there is a wall.
+	fn get_solid(chunk: &Chunk, pos: &ChunkSubPos) -> (bool, bool) {
+		let tile: &Tile = chunk.get(pos);
+		let wall: &Wall = chunk.get(pos);
+		(tile.id.get_collision_type() == CollisionType::CollidesPlayer, wall.id != wall::AIR)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile::{self, Tile};
+	use crate::world::World;
+
+	/// A stone floor from y 0 to 4 with an empty chunk above it.
+	fn create_floor_world() -> World {
+		create_world(vec![
+			(ChunkPos::new(0, 0), create_chunk(|_, y| if y < 5 { tile::STONE } else { tile::AIR })),
+			(ChunkPos::new(0, 1), create_chunk(|_, _| tile::AIR)),
+		])
+	}
+
+	fn get_tile_height(world: &World, x: i32) -> Option<u32> {
+		world.get_column_height(x).and_then(|height| height.tile)
+	}
+
+	#[test]
+	fn placing_above_the_top_raises_it() {
+		let mut world = create_floor_world();
+		assert_eq!(get_tile_height(&world, 3), Some(4));
+		world.set(&WorldPos::new(3, 30), Tile::id(tile::STONE));
+		assert_eq!(get_tile_height(&world, 3), Some(30));
+		world.set(&WorldPos::new(3, 10), Tile::id(tile::STONE));
+		assert_eq!(get_tile_height(&world, 3), Some(30));
+		assert_eq!(get_tile_height(&world, 4), Some(4));
+	}
+
+	#[test]
+	fn removing_the_top_scans_down_to_the_next_one() {
+		let mut world = create_floor_world();
+		world.set(&WorldPos::new(3, 30), Tile::id(tile::STONE));
+		world.set(&WorldPos::new(3, 10), Tile::id(tile::STONE));
+
+		world.set(&WorldPos::new(3, 30), Tile::air());
+		assert_eq!(get_tile_height(&world, 3), Some(10));
+		world.set(&WorldPos::new(3, 10), Tile::air());
+		assert_eq!(get_tile_height(&world, 3), Some(4));
+		// Removing a tile below the top changes nothing.
+		world.set(&WorldPos::new(3, 2), Tile::air());
+		assert_eq!(get_tile_height(&world, 3), Some(4));
+	}
+
+	#[test]
+	fn unloading_the_chunk_with_the_top_falls_back_to_the_chunks_below() {
+		let mut world = create_floor_world();
+		world.set(&WorldPos::new(3, 30), Tile::id(tile::STONE));
+
+		assert!(world.unload_chunk(&ChunkPos::new(0, 1)).unwrap().is_some());
+		assert_eq!(get_tile_height(&world, 3), Some(4));
+		assert!(world.unload_chunk(&ChunkPos::new(0, 0)).unwrap().is_some());
+		assert_eq!(world.get_column_height(3), None);
+	}
+
+	#[test]
+	fn surface_height_asks_the_generator_without_the_surface_chunk() {
+		let mut world = create_floor_world();
+		let generated = world.chunk_generator.get_surface_height(3);
+		let surface_chunk = *WorldPos::new(3, generated).get_chunk_pos();
+		assert!(surface_chunk.y > 1, "The generated surface needs to be above the loaded chunks");
+		assert_eq!(world.get_surface_height(3), generated);
+
+		// With the surface chunk loaded the loaded tiles win, even if they are far below.
+		world.insert_chunk(surface_chunk, create_chunk(|_, _| tile::AIR));
+		assert_eq!(world.get_surface_height(3), 4);
+
+		// Without any solid tile in the column the generator is asked again.
+		for y in 0..5 {
+			world.set(&WorldPos::new(3, y), Tile::air());
+		}
+		assert_eq!(world.get_surface_height(3), generated);
+	}
+}
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::misc::pos::WorldPos;
+use crate::world::edit::WorldEdit;
+use crate::world::Layer;
+use crate::world::tile_entity::TileEntity;
+
+/// How many actions can be undone before the oldest ones get forgotten.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// The state every position had before a single user level action changed it.
+#[derive(Default)]
+pub struct Action {
+	pub(crate) id: u64,
+	pub(crate) edit: WorldEdit,
+	pub(crate) tile_entities: HashMap<WorldPos, TileEntity>,
+}
+
+impl Action {
+	/// Remembers `old` as the state of `pos` unless the action already knows an older one.
+	pub(crate) fn record<C: Layer>(&mut self, pos: &WorldPos, old: C, entity: Option<TileEntity>) {
+		if self.edit.get::<C>(pos).is_none() {
+			self.edit.set(pos, old);
+		}
+		if let Some(entity) = entity {
+			self.tile_entities.entry(*pos).or_insert(entity);
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.edit.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.edit.is_empty()
+	}
+}
+
+/// The undo and redo stacks of a world. Changes only get recorded while an action is open,
+/// or while the world redirects the reactions of an earlier action back into it.
+pub struct History {
+	pub limit: usize,
+	undo: VecDeque<Action>,
+	redo: Vec<Action>,
+	current: Option<Action>,
+	/// The id of the action that changes get recorded into.
+	target: Option<u64>,
+	next_id: u64,
+}
+
+impl Default for History {
+	fn default() -> Self {
+		Self {
+			limit: HISTORY_LIMIT,
+			undo: VecDeque::new(),
+			redo: Vec::new(),
+			current: None,
+			target: None,
+			next_id: 0,
+		}
+	}
+}
+
+impl History {
+	/// Starts recording a new action, closing the one that was open.
+	pub fn begin_action(&mut self) {
+		self.end_action();
+		let action = self.create_action();
+		self.target = Some(action.id);
+		self.current = Some(action);
+	}
+
+	/// Closes the open action. Actions that did not change anything are dropped.
+	pub fn end_action(&mut self) {
+		self.target = None;
+		if let Some(action) = self.current.take().filter(|action| !action.is_empty()) {
+			self.redo.clear();
+			self.push_undo(action);
+		}
+	}
+
+	pub fn is_recording(&self) -> bool {
+		self.current.is_some()
+	}
+
+	pub fn can_undo(&self) -> bool {
+		!self.undo.is_empty()
+	}
+
+	pub fn can_redo(&self) -> bool {
+		!self.redo.is_empty()
+	}
+
+	pub fn clear(&mut self) {
+		self.undo.clear();
+		self.redo.clear();
+		self.current = None;
+		self.target = None;
+	}
+
+	pub(crate) fn create_action(&mut self) -> Action {
+		self.next_id += 1;
+		Action { id: self.next_id, ..Action::default() }
+	}
+
+	pub(crate) fn get_target(&self) -> Option<u64> {
+		self.target
+	}
+
+	/// Records changes into the action `target` from now on, returning the previous target.
+	/// Changes for actions that got undone or forgotten are not recorded anywhere.
+	pub(crate) fn set_target(&mut self, target: Option<u64>) -> Option<u64> {
+		std::mem::replace(&mut self.target, target)
+	}
+
+	/// Gets the id of the action that gets undone next.
+	pub(crate) fn get_undo_id(&self) -> Option<u64> {
+		self.undo.back().map(|action| action.id)
+	}
+
+	pub(crate) fn record<C: Layer>(&mut self, pos: &WorldPos, old: C, entity: Option<TileEntity>) {
+		let id = match self.target {
+			Some(id) => id,
+			None => return,
+		};
+		let action = self.current.iter_mut()
+			.chain(self.undo.iter_mut().rev())
+			.find(|action| action.id == id);
+		if let Some(action) = action {
+			action.record(pos, old, entity);
+		}
+	}
+
+	pub(crate) fn pop_undo(&mut self) -> Option<Action> {
+		self.undo.pop_back()
+	}
+
+	pub(crate) fn pop_redo(&mut self) -> Option<Action> {
+		self.redo.pop()
+	}
+
+	pub(crate) fn push_undo(&mut self, action: Action) {
+		self.undo.push_back(action);
+		while self.undo.len() > self.limit {
+			self.undo.pop_front();
+		}
+	}
+
+	pub(crate) fn push_redo(&mut self, action: Action) {
+		self.redo.push(action);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::item::ItemId;
+	use crate::item::stack::ItemStack;
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile::{Tile, TileId};
+	use crate::world::tile_entity::TileEntity;
+	use crate::world::{tile, World};
+
+	fn create_sand_world() -> World {
+		create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| match (x, y) {
+			(_, 0) | (5, 1) => tile::STONE,
+			(5, 10..=11) => tile::SAND,
+			_ => tile::AIR,
+		}))])
+	}
+
+	fn get_column(world: &World) -> Vec<TileId> {
+		(0..16).map(|y| world.get::<Tile>(&WorldPos::new(5, y)).unwrap().id).collect()
+	}
+
+	fn tick_until_landed(world: &mut World) {
+		for _ in 0..1000 {
+			if world.get_falling_tiles().is_empty() {
+				return;
+			}
+			world.tick().unwrap();
+		}
+		panic!("Tiles are still falling");
+	}
+
+	#[test]
+	fn undo_puts_back_tiles_that_fell_and_landed() {
+		let mut world = create_sand_world();
+		let before = get_column(&world);
+
+		// Removing the lower sand makes the one above fall down onto the stone.
+		world.begin_action();
+		world.set(&WorldPos::new(5, 10), Tile::air());
+		world.end_action();
+		tick_until_landed(&mut world);
+		let after = get_column(&world);
+		assert_eq!(after[2], tile::SAND);
+
+		assert!(world.undo());
+		assert_eq!(get_column(&world), before);
+		assert!(world.get_falling_tiles().is_empty());
+		assert!(world.redo());
+		assert_eq!(get_column(&world), after);
+		assert!(world.get_falling_tiles().is_empty());
+	}
+
+	#[test]
+	fn undo_lands_tiles_that_are_still_falling() {
+		let mut world = create_sand_world();
+		let before = get_column(&world);
+
+		world.begin_action();
+		world.set(&WorldPos::new(5, 10), Tile::air());
+		world.tick().unwrap();
+		assert_eq!(world.get_falling_tiles().len(), 1);
+
+		assert!(world.undo());
+		assert_eq!(get_column(&world), before);
+		assert!(world.get_falling_tiles().is_empty());
+		assert!(world.redo());
+		assert_eq!(get_column(&world)[2], tile::SAND);
+		assert_eq!(get_column(&world)[11], tile::AIR);
+	}
+
+	#[test]
+	fn undo_and_redo_do_not_drop_anything() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, y| if y == 0 { tile::STONE } else { tile::AIR }))]);
+		let origin = WorldPos::new(3, 1);
+		world.begin_action();
+		world.place_object(&origin, tile::CHEST).unwrap();
+		world.end_action();
+		if let Some(TileEntity::Chest(chest)) = world.get_tile_entity_mut(&origin) {
+			chest.inventory.insert(ItemStack::new(ItemId::Block(tile::STONE), 3));
+		}
+
+		assert!(world.undo());
+		assert!(world.get_tile_entity(&origin).is_none());
+		assert!(world.redo());
+		assert!(world.get_drops().is_empty());
+		match world.get_tile_entity(&origin) {
+			Some(TileEntity::Chest(chest)) => assert!(chest.inventory.get_slots().iter().flatten().any(|stack| *stack == ItemStack::new(ItemId::Block(tile::STONE), 3))),
+			_ => panic!("Chest did not come back"),
+		}
+	}
+}
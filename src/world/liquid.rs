@@ -0,0 +1,78 @@
+use crate::misc::util::Direction;
+use crate::world::neighbor::{NeighborAware, NeighborMatrix, NeighborType};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LiquidKind {
+	Water,
+	Lava,
+	Honey,
+}
+
+impl LiquidKind {
+	pub fn get_id(&self) -> u8 {
+		match self {
+			LiquidKind::Water => 0,
+			LiquidKind::Lava => 1,
+			LiquidKind::Honey => 2,
+		}
+	}
+
+	pub fn from_id(id: u8) -> Option<LiquidKind> {
+		match id {
+			0 => Some(LiquidKind::Water),
+			1 => Some(LiquidKind::Lava),
+			2 => Some(LiquidKind::Honey),
+			_ => None,
+		}
+	}
+}
+
+/// The liquid inside of a tile. An `amount` of 0 means there is no liquid, 255 is a full tile.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Liquid {
+	pub kind: LiquidKind,
+	pub amount: u8,
+	matrix: NeighborMatrix,
+}
+
+impl Liquid {
+	pub fn air() -> Liquid {
+		Self {
+			kind: LiquidKind::Water,
+			amount: 0,
+			matrix: NeighborMatrix::default(),
+		}
+	}
+
+	pub fn new(kind: LiquidKind, amount: u8) -> Liquid {
+		Self {
+			kind,
+			amount,
+			matrix: NeighborMatrix::default(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.amount == 0
+	}
+}
+
+impl NeighborAware for Liquid {
+	fn get_neighbor_matrix(&self) -> &NeighborMatrix {
+		&self.matrix
+	}
+
+	fn get_neighbor_matrix_mut(&mut self) -> &mut NeighborMatrix {
+		&mut self.matrix
+	}
+
+	fn apply_neighbor(&self, neighbor: &Liquid, _direction: Direction) -> NeighborType {
+		if neighbor.is_empty() {
+			NeighborType::Air
+		} else if neighbor.kind == self.kind {
+			NeighborType::Same
+		} else {
+			NeighborType::Transitional
+		}
+	}
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use crate::item::ItemKind;
+use crate::misc::pos::WorldPos;
+use crate::misc::util::CollisionType;
+use crate::player::BLOCK_USE_TIME;
+use crate::world::{tile, World};
+use crate::world::object::{ObjectType, PlaceError};
+use crate::world::physics::Aabb;
+use crate::world::support::ItemDrop;
+use crate::world::tile::{Tile, TileId};
+
+/// Damage heals once a tile has not been hit for this many ticks.
+const DAMAGE_RESET_TICKS: u64 = 300;
+/// Players pick up drops within this many tiles of their center.
+pub const PICKUP_RANGE: f32 = 5.25;
+/// How many drops can lie around in the world. The oldest ones disappear once there are more.
+pub const MAX_DROPS: usize = 400;
+
+/// Damage dealt to a tile that did not break yet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TileDamage {
+	pub id: TileId,
+	pub damage: u32,
+	last_hit: u64,
+}
+
+impl TileDamage {
+	/// How close the tile is to breaking, between 0 and 1.
+	pub fn get_progress(&self) -> f32 {
+		(self.damage as f32 / self.id.get_hardness().max(1) as f32).min(1.0)
+	}
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MineResult {
+	/// There is no tile or its chunk is not loaded.
+	Nothing,
+	/// The tool is too weak to damage the tile.
+	TooWeak,
+	Damaged,
+	Broken,
+}
+
+impl World {
+	/// Hits the tile at `pos` with a tool of `power` and breaks it once the damage reaches its hardness.
+	/// Broken tiles drop like any other tile broken through `break_tile`.
+	pub fn mine(&mut self, pos: &WorldPos, power: u32) -> MineResult {
+		let id = match self.get::<Tile>(pos) {
+			Some(tile) if tile.id != tile::AIR => tile.id,
+			_ => return MineResult::Nothing,
+		};
+		if power == 0 || power < id.get_required_power() {
+			return MineResult::TooWeak;
+		}
+
+		let tick = self.tick_count;
+		let damage = self.tile_damage.entry(*pos).or_insert(TileDamage { id, damage: 0, last_hit: tick });
+		// The tile got replaced since it was last hit.
+		if damage.id != id {
+			*damage = TileDamage { id, damage: 0, last_hit: tick };
+		}
+		damage.damage += power;
+		damage.last_hit = tick;
+		if damage.damage < id.get_hardness() {
+			return MineResult::Damaged;
+		}
+
+		self.tile_damage.remove(pos);
+		self.break_tile(pos);
+		MineResult::Broken
+	}
+
+	pub fn get_tile_damage(&self, pos: &WorldPos) -> Option<&TileDamage> {
+		self.tile_damage.get(pos)
+	}
+
+	pub fn get_damaged_tiles(&self) -> &HashMap<WorldPos, TileDamage> {
+		&self.tile_damage
+	}
+
+	/// Places `id` at `pos`, which needs to be empty and next to a tile that is not air.
+	/// Tiles that collide can not be placed inside of a player. Objects get placed through `place_object` instead.
+	pub fn place_tile(&mut self, pos: &WorldPos, id: TileId) -> Result<(), PlaceError> {
+		if ObjectType::get(&id).is_some() {
+			return self.place_object(pos, id);
+		}
+
+		match self.get::<Tile>(pos) {
+			None => return Err(PlaceError::NotLoaded(*pos)),
+			Some(tile) if tile.id != tile::AIR => return Err(PlaceError::Obstructed(*pos)),
+			Some(_) => {}
+		}
+
+		let attached = pos.get_neighbors().any(|(_, neighbor)| {
+			matches!(self.get::<Tile>(&neighbor), Some(tile) if tile.id.get_collision_type() != CollisionType::Nothing)
+		});
+		if !attached {
+			return Err(PlaceError::NoAnchor);
+		}
+
+		if id.get_collision_type() == CollisionType::CollidesPlayer {
+			let aabb = Aabb::new(pos.get_x() as f32, pos.get_y() as f32, 1.0, 1.0);
+			if self.players.iter().any(|player| player.get_aabb().overlaps(&aabb)) {
+				return Err(PlaceError::Obstructed(*pos));
+			}
+		}
+
+		self.set(pos, Tile::id(id));
+		Ok(())
+	}
+
+	/// Lets every player that holds the mine or place button use its held item on its target, and heals tiles that were left alone.
+	/// Mining needs a tool and hands the drops to the player, placing uses up one of the held blocks.
+	pub(crate) fn tick_player_actions(&mut self) {
+		// Players keep what they mine, so undoing an open action must not bring their tiles back.
+		let action = self.history.set_target(None);
+		for index in 0..self.players.len() {
+			let player = &mut self.players[index];
+			let target = match player.target {
+				Some(target) if (player.mining || player.placing) && player.can_reach(&target) => target,
+				_ => continue,
+			};
+
+			if player.mining {
+				let power = match player.get_tool() {
+					Some((power, use_time)) if player.try_use(use_time) => power,
+					_ => continue,
+				};
+				let start = self.drops.len();
+				self.mine(&target, power);
+				self.give_drops(index, start);
+			} else {
+				let id = match player.get_held_item().and_then(|stack| stack.get_type()).map(|item| item.kind) {
+					Some(ItemKind::Block(id)) if player.try_use(BLOCK_USE_TIME) => id,
+					_ => continue,
+				};
+				// Failing to place is normal while the cursor is over something.
+				if self.place_tile(&target, id).is_ok() {
+					let player = &mut self.players[index];
+					player.inventory.take(player.selected_slot, 1);
+				}
+			}
+		}
+		self.history.set_target(action);
+
+		let tick = self.tick_count;
+		self.tile_damage.retain(|_, damage| tick - damage.last_hit < DAMAGE_RESET_TICKS);
+	}
+
+	/// Moves the drops from `start` on into the inventory of the player. Whatever does not fit stays in the world.
+	fn give_drops(&mut self, player: usize, start: usize) {
+		let drops: Vec<ItemDrop> = self.drops.drain(start..).collect();
+		for drop in drops {
+			if let Some(stack) = self.players[player].inventory.insert(drop.stack) {
+				self.drops.push(ItemDrop { pos: drop.pos, stack });
+			}
+		}
+	}
+
+	/// Hands the drops lying in the world to the players in range and removes the oldest drops once there are too many.
+	pub(crate) fn tick_drops(&mut self) {
+		let drops = std::mem::take(&mut self.drops);
+		for drop in drops {
+			let center = drop.pos.get_center();
+			let mut rest = Some(drop.stack);
+			for player in &mut self.players {
+				if let Some(stack) = rest.filter(|_| player.get_center().get_distance(&center) <= PICKUP_RANGE) {
+					rest = player.inventory.insert(stack);
+				}
+			}
+			if let Some(stack) = rest {
+				self.drops.push(ItemDrop { pos: drop.pos, stack });
+			}
+		}
+
+		let excess = self.drops.len().saturating_sub(MAX_DROPS);
+		self.drops.drain(..excess);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::item::{ItemId, IRON_BAR};
+	use crate::item::stack::ItemStack;
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::player::Player;
+	use crate::world::mining::{MAX_DROPS, MineResult};
+	use crate::world::support::ItemDrop;
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::{tile, World};
+	use crate::world::tile::{Tile, TileId};
+
+	/// How many hits a tool of `power` needs to break `id`, if it can at all.
+	fn mine(id: TileId, power: u32) -> Option<u32> {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| if (x, y) == (3, 3) { id } else { tile::STONE }))]);
+		let pos = WorldPos::new(3, 3);
+		for hits in 1..=100 {
+			match world.mine(&pos, power) {
+				MineResult::Broken => return Some(hits),
+				MineResult::Damaged => {}
+				MineResult::TooWeak | MineResult::Nothing => return None,
+			}
+		}
+		panic!("Tile did not break");
+	}
+
+	#[test]
+	fn corruption_tiles_mine_like_their_counterparts() {
+		assert_eq!(mine(tile::CORRUPT_GRASS, 35), mine(tile::GRASS, 35));
+		assert_eq!(ItemId::from_tile_drop(&tile::CORRUPT_GRASS), Some(ItemId::Block(tile::DIRT)));
+
+		// Ebonstone needs a better pickaxe than stone and takes longer to break.
+		assert_eq!(mine(tile::EBONSTONE, 55), None);
+		assert!(mine(tile::EBONSTONE, 65).unwrap() > mine(tile::STONE, 65).unwrap());
+		assert_eq!(mine(tile::DEMONITE_ORE, 50), None);
+		assert_eq!(mine(tile::DEMONITE_ORE, 55), Some(2));
+	}
+
+	#[test]
+	fn hardmode_ores_get_harder() {
+		let hardness: Vec<u32> = [tile::STONE, tile::COBALT_ORE, tile::MYTHRIL_ORE, tile::ADAMANTITE_ORE].iter().map(TileId::get_hardness).collect();
+		assert!(hardness.windows(2).all(|pair| pair[0] < pair[1]));
+		assert_eq!(mine(tile::ADAMANTITE_ORE, 110), None);
+	}
+
+	#[test]
+	fn players_in_range_pick_up_drops() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, _| tile::AIR))]);
+		let id = world.player_join(Player::new());
+		let player = world.acquire_player_mut(&id);
+		player.pos_x = 2.5;
+		player.pos_y = 1.0;
+
+		let near = ItemDrop { pos: WorldPos::new(4, 2), stack: ItemStack::new(IRON_BAR, 3) };
+		let far = ItemDrop { pos: WorldPos::new(20, 2), stack: ItemStack::new(IRON_BAR, 5) };
+		world.drops.extend([near, far]);
+		world.tick_drops();
+
+		assert_eq!(world.get_drops(), &[far]);
+		assert_eq!(world.acquire_player(&id).inventory.count(&IRON_BAR), 3);
+	}
+
+	#[test]
+	fn oldest_drops_disappear_first() {
+		let mut world = create_world(vec![]);
+		for y in 0..MAX_DROPS as u32 + 10 {
+			world.drops.push(ItemDrop { pos: WorldPos::new(0, y), stack: ItemStack::new(IRON_BAR, 1) });
+		}
+		world.tick_drops();
+
+		assert_eq!(world.get_drops().len(), MAX_DROPS);
+		assert_eq!(world.get_drops()[0].pos, WorldPos::new(0, 10));
+	}
+
+	/// Ticks the players and lets them mine and place like a world tick would, until `done` or a hundred ticks went by.
+	fn tick_players(world: &mut World, done: impl Fn(&World) -> bool) {
+		for _ in 0..100 {
+			let mut players = std::mem::take(&mut world.players);
+			for player in &mut players {
+				player.tick(world);
+			}
+			world.players = players;
+			world.tick_player_actions();
+			if done(world) {
+				return;
+			}
+		}
+		panic!("The players did not get done");
+	}
+
+	#[test]
+	fn undo_keeps_what_players_mined_and_placed() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, y| if y == 0 { tile::STONE } else { tile::AIR }))]);
+		let id = world.player_join(Player::new());
+		let player = world.acquire_player_mut(&id);
+		player.pos_x = 2.5;
+		player.pos_y = 1.0;
+		player.inventory.set(1, Some(ItemStack::new(ItemId::Block(tile::DIRT), 5)));
+		let get_id = |world: &World, x: i32, y: u32| world.get::<Tile>(&WorldPos::new(x, y)).unwrap().id;
+
+		world.begin_action();
+		world.set(&WorldPos::new(10, 1), Tile::id(tile::STONE));
+		let player = world.acquire_player_mut(&id);
+		player.target = Some(WorldPos::new(4, 0));
+		player.mining = true;
+		tick_players(&mut world, |world| get_id(world, 4, 0) == tile::AIR);
+
+		let player = world.acquire_player_mut(&id);
+		player.mining = false;
+		player.placing = true;
+		player.selected_slot = 1;
+		player.target = Some(WorldPos::new(5, 1));
+		tick_players(&mut world, |world| get_id(world, 5, 1) == tile::DIRT);
+		world.end_action();
+
+		// Only the change outside of the players gets undone.
+		assert!(world.undo());
+		assert_eq!(get_id(&world, 10, 1), tile::AIR);
+		assert_eq!((get_id(&world, 4, 0), get_id(&world, 5, 1)), (tile::AIR, tile::DIRT));
+		assert!(!world.undo());
+
+		let inventory = &world.acquire_player(&id).inventory;
+		assert_eq!(inventory.count(&ItemId::Block(tile::STONE)), 1);
+		assert_eq!(inventory.count(&ItemId::Block(tile::DIRT)), 4);
+		assert!(world.get_drops().is_empty());
+	}
+}
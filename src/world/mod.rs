@@ -1,28 +1,81 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::sync::Arc;
 
+use crossbeam::channel::Receiver;
 use neighbor::NeighborAware;
 
 use crate::gen::WorldGenerator;
+use crate::item::ItemId;
+use crate::item::recipe::{CRAFTING_RANGE_X, CRAFTING_RANGE_Y, CraftError, RecipeRegistry};
+use crate::item::stack::ItemStack;
 use crate::misc::pos::{ChunkPos, ChunkSubPos, WorldPos};
 use crate::misc::util::{CHUNK_SIZE, Direction};
 use crate::Player;
+use crate::world::edit::{PendingEdit, PendingEdits, WorldEdit};
+use crate::world::event::{EventBus, EventKind, WorldEvent};
+use crate::world::falling::{FallingTile, FallResult};
+use crate::world::heightmap::{ColumnHeight, Heightmap};
+use crate::world::history::{Action, History};
+use crate::world::liquid::Liquid;
+use crate::world::mining::TileDamage;
 use crate::world::neighbor::NeighborMatrix;
-use crate::world::tile::Tile;
-use crate::world::wall::Wall;
+use crate::world::object::{ObjectType, PlaceError};
+use crate::world::paint::{PaintId, PaintPalette, Paintable};
+use crate::world::region::{Clipboard, ClipboardCell, Region, Transform};
+use crate::world::save::WorldSave;
+use crate::world::schematic::Schematic;
+use crate::world::support::ItemDrop;
+use crate::world::tick::{RandomTicker, ScheduledUpdates, Tickable};
+use crate::world::tile::{Tile, TileId};
+use crate::world::tile_entity::TileEntity;
+use crate::world::wall::{Wall, WallId};
 
 pub mod tile;
 pub mod wall;
 pub mod tick;
 pub mod neighbor;
+pub mod save;
+pub mod paint;
+pub mod falling;
+pub mod support;
+pub mod tile_entity;
+pub mod object;
+pub mod liquid;
+pub mod event;
+pub mod edit;
+pub mod region;
+pub mod schematic;
+pub mod history;
+pub mod query;
+pub mod heightmap;
+pub mod physics;
+pub mod mining;
+#[cfg(test)]
+pub(crate) mod testing;
 
 // un hard code this
 const RENDER_DISTANCE: i32 = 16;
+/// Chunks further than this from every player get unloaded. A bit more than the render distance so chunks at the edge do not reload all the time.
+const UNLOAD_DISTANCE: i32 = RENDER_DISTANCE + 2;
 
 pub struct World {
 	players: Vec<Player>,
-	pub chunk_updates: HashSet<ChunkPos>,
+	events: EventBus,
 	chunks: HashMap<ChunkPos, Chunk>,
 	chunk_generator: WorldGenerator,
+	save: Option<WorldSave>,
+	falling_tiles: Vec<FallingTile>,
+	drops: Vec<ItemDrop>,
+	random_ticker: RandomTicker,
+	scheduled_updates: ScheduledUpdates,
+	pending_edits: HashMap<ChunkPos, PendingEdits>,
+	history: History,
+	heightmap: Heightmap,
+	tile_damage: HashMap<WorldPos, TileDamage>,
+	recipes: RecipeRegistry,
+	paints: PaintPalette,
+	tick_count: u64,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
@@ -40,18 +93,40 @@ impl Default for PlayerId {
 
 impl Default for World {
 	fn default() -> World {
+		World::new(69)
+	}
+}
+
+impl World {
+	pub fn new(seed: u64) -> World {
 		Self {
 			players: Vec::new(),
-			chunk_updates: HashSet::new(),
+			events: EventBus::default(),
 			chunks: HashMap::new(),
-			chunk_generator: WorldGenerator::new(69),
+			chunk_generator: WorldGenerator::new(seed),
+			save: None,
+			falling_tiles: Vec::new(),
+			drops: Vec::new(),
+			random_ticker: RandomTicker::new(seed),
+			scheduled_updates: ScheduledUpdates::default(),
+			pending_edits: HashMap::new(),
+			history: History::default(),
+			heightmap: Heightmap::default(),
+			tile_damage: HashMap::new(),
+			recipes: RecipeRegistry::default(),
+			paints: PaintPalette::default(),
+			tick_count: 0,
 		}
 	}
-}
 
-impl World {
 	// FIXME Not multiplayer ready because if a player leaves the ids will be misaligned
-	pub fn player_join(&mut self, player: Player) -> PlayerId {
+	/// Adds the player, standing on the surface above x 0 if it is still at the origin.
+	pub fn player_join(&mut self, mut player: Player) -> PlayerId {
+		if player.pos_x == 0.0 && player.pos_y == 0.0 {
+			player.pos_x = 0.5;
+			player.pos_y = self.get_surface_height(0) as f32 + 1.0;
+		}
+
 		let id = PlayerId {
 			id: self.players.len()
 		};
@@ -67,13 +142,64 @@ impl World {
 		self.players.get(id.id).expect("Could not find player")
 	}
 
+	pub fn set_recipes(&mut self, recipes: RecipeRegistry) {
+		self.recipes = recipes;
+	}
+
+	pub fn get_recipes(&self) -> &RecipeRegistry {
+		&self.recipes
+	}
 
-	pub fn tick(&mut self) {
-		for player in &mut self.players {
-			player.pos_x += player.vel_x * player.speed;
-			player.pos_y += player.vel_y * player.speed;
+	pub fn set_paints(&mut self, paints: PaintPalette) {
+		self.paints = paints;
+	}
+
+	pub fn get_paints(&self) -> &PaintPalette {
+		&self.paints
+	}
+
+	/// The tiles within crafting range of the player.
+	pub fn get_stations(&self, id: &PlayerId) -> HashSet<TileId> {
+		match self.acquire_player(id).get_center().get_tile_pos() {
+			Some(center) => self.get_tiles_near(&center, CRAFTING_RANGE_X, CRAFTING_RANGE_Y),
+			None => HashSet::new(),
 		}
+	}
+
+	/// Every recipe the player can craft right now with its inventory and the stations around it.
+	pub fn get_craftable_recipes(&self, id: &PlayerId) -> Vec<usize> {
+		self.recipes.get_craftable(&self.acquire_player(id).inventory, &self.get_stations(id))
+	}
+
+	/// Crafts `recipe` out of the inventory of the player. Nothing changes if the recipe can not be crafted.
+	pub fn craft(&mut self, id: &PlayerId, recipe: usize) -> Result<(), CraftError> {
+		let stations = self.get_stations(id);
+		let recipe = self.recipes.get(recipe).ok_or(CraftError::UnknownRecipe)?;
+		recipe.craft(&mut self.players.get_mut(id.id).expect("Could not find player").inventory, &stations)
+	}
+
 
+	/// Ticks everything in the world and loads, generates and unloads the chunks around the players.
+	/// A chunk that fails to load gets generated instead. The first error that happened gets returned once the whole tick ran.
+	pub fn tick(&mut self) -> io::Result<()> {
+		let mut result = Ok(());
+		self.tick_count += 1;
+		let mut players = std::mem::take(&mut self.players);
+		for player in &mut players {
+			player.tick(self);
+		}
+		self.players = players;
+		self.tick_player_actions();
+		self.tick_drops();
+
+		self.tick_falling_tiles();
+		self.tick_scheduled_updates();
+		self.random_tick();
+		for chunk in self.chunks.values_mut() {
+			chunk.tick_tile_entities();
+		}
+
+		let mut missing_chunks = Vec::new();
 		for player in &self.players {
 			let lookup_pos = ChunkPos::from_player(player);
 
@@ -82,7 +208,7 @@ impl World {
 					for y in (-RENDER_DISTANCE)..RENDER_DISTANCE {
 						if let Some(pos) = lookup_pos.shift_amount(Direction::Down, y) {
 							if !self.chunks.contains_key(&pos) {
-								self.chunk_generator.add_chunk(&pos);
+								missing_chunks.push(pos);
 							}
 						}
 					}
@@ -91,50 +217,220 @@ impl World {
 		}
 
 
+		result = result.and(self.load_chunks(missing_chunks));
 		if let Some(new_chunks) = self.chunk_generator.generate_chunks() {
 			for (pos, chunk) in new_chunks {
-				self.chunks.insert(pos, chunk);
-				let chunk = self.chunks.get(&pos).unwrap();
-				self.update_borders::<Tile>(&pos, chunk);
-				self.update_borders::<Wall>(&pos, chunk);
-				for dir in Direction::iter() {
-					if let Some(neighbor) = pos.shift(dir) { self.chunk_updates.insert(neighbor); }
+				self.insert_chunk(pos, chunk);
+			}
+		}
+		result.and(self.unload_far_chunks())
+	}
+
+	/// Loads the chunks from the save or queues them for generation. A chunk that fails to load gets generated instead,
+	/// the error is only returned on that first attempt because the chunk stays queued until it is generated.
+	fn load_chunks(&mut self, chunks: Vec<ChunkPos>) -> io::Result<()> {
+		let mut result = Ok(());
+		for pos in chunks {
+			if self.chunk_generator.is_queued(&pos) {
+				continue;
+			}
+
+			match self.save.as_ref().filter(|save| save.has_chunk(&pos)).map(|save| save.load_chunk(&pos)) {
+				Some(Ok((chunk, updates))) => {
+					self.insert_chunk(pos, chunk);
+					for (sub_pos, delay) in updates {
+						self.schedule_update(&WorldPos::from_chunk(&pos, sub_pos.x, sub_pos.y), delay);
+					}
+				}
+				Some(Err(error)) => {
+					result = result.and(Err(io::Error::new(error.kind(), format!("Could not load chunk {:?}, generating it instead. {}", pos, error))));
+					self.chunk_generator.add_chunk(&pos);
 				}
+				None => self.chunk_generator.add_chunk(&pos),
 			}
 		}
+		result
+	}
+
+	/// Unloads and stores the chunks that are out of range of every player.
+	/// Worlds without players or without a save keep all of their chunks, so no edit gets lost.
+	fn unload_far_chunks(&mut self) -> io::Result<()> {
+		let centers: Vec<ChunkPos> = self.players.iter().map(ChunkPos::from_player).collect();
+		if centers.is_empty() || self.save.is_none() {
+			return Ok(());
+		}
+
+		let far: Vec<ChunkPos> = self.chunks.keys()
+			.filter(|pos| centers.iter().all(|center| {
+				(pos.x as i32 - center.x as i32).abs() > UNLOAD_DISTANCE || (pos.y as i32 - center.y as i32).abs() > UNLOAD_DISTANCE
+			}))
+			.copied()
+			.collect();
+		for pos in far {
+			self.unload_chunk(&pos)?;
+		}
+		Ok(())
 	}
 
-	fn update_borders<C: NeighborAware>(&self, pos: &ChunkPos, chunk: &Chunk) where Chunk: Grid<C> {
+	fn insert_chunk(&mut self, pos: ChunkPos, mut chunk: Chunk) {
+		let mut pending: Vec<PendingEdit> = self.pending_edits.remove(&pos).unwrap_or_default().into_values().collect();
+		// Every key is unique, the order only keeps the drops of replaced chests the same every time.
+		pending.sort_by_key(|edit| (edit.get_layer(), edit.get_pos().y, edit.get_pos().x));
+		if !pending.is_empty() {
+			for edit in &pending {
+				let sub_pos = edit.get_pos();
+				let removed = chunk.apply_pending_edit(edit);
+				self.drop_contents(&WorldPos::from_chunk(&pos, sub_pos.x, sub_pos.y), removed);
+			}
+			NeighborMatrix::update_grid(Grid::<Tile>::get_grid_mut(&mut chunk));
+			NeighborMatrix::update_grid(Grid::<Wall>::get_grid_mut(&mut chunk));
+			NeighborMatrix::update_grid(Grid::<Liquid>::get_grid_mut(&mut chunk));
+		}
+
+		self.update_borders::<Tile>(&pos, &mut chunk);
+		self.update_borders::<Wall>(&pos, &mut chunk);
+		self.update_borders::<Liquid>(&pos, &mut chunk);
+		self.chunks.insert(pos, chunk);
+		self.heightmap.add_chunk(&pos, &self.chunks);
+		self.events.emit(WorldEvent::ChunkLoaded(pos));
+
+		for edit in &pending {
+			let sub_pos = edit.get_pos();
+			let world_pos = WorldPos::from_chunk(&pos, sub_pos.x, sub_pos.y);
+			self.update_support(&world_pos);
+			self.update_falling(&world_pos);
+		}
+	}
+
+	/// Queues a change to a chunk that is not loaded. A later change to the same position and layer replaces it.
+	fn defer<C: Layer>(&mut self, pos: &WorldPos, object: C) {
+		let edit = C::create_pending(*pos.get_chunk_sub_pos(), object);
+		self.pending_edits.entry(*pos.get_chunk_pos()).or_default().insert(edit.get_key(), edit);
+	}
+
+	/// How many changes are waiting for their chunk to get loaded.
+	pub fn get_pending_edit_count(&self) -> usize {
+		self.pending_edits.values().map(HashMap::len).sum()
+	}
+
+	/// The changes waiting for the chunk at `pos`.
+	pub fn get_pending_edits(&self, pos: &ChunkPos) -> impl Iterator<Item = &PendingEdit> {
+		self.pending_edits.get(pos).into_iter().flat_map(HashMap::values)
+	}
+
+	/// Removes a chunk from the world, saving it first if the world has a save.
+	pub fn unload_chunk(&mut self, pos: &ChunkPos) -> io::Result<Option<Chunk>> {
+		if let (Some(save), Some(chunk)) = (&self.save, self.chunks.get(pos)) {
+			save.save_chunk(pos, chunk, &self.get_pending_updates(pos))?;
+		}
+
+		let chunk = self.chunks.remove(pos);
+		if chunk.is_some() {
+			self.scheduled_updates.remove_chunk(pos);
+			self.heightmap.remove_chunk(pos, &self.chunks);
+			self.events.emit(WorldEvent::ChunkUnloaded(*pos));
+		}
+		Ok(chunk)
+	}
+
+	/// The highest solid tile and highest wall of column `x` within the loaded chunks.
+	pub fn get_column_height(&self, x: i32) -> Option<&ColumnHeight> {
+		self.heightmap.get(x)
+	}
+
+	/// The y of the highest solid tile at `x`. Uses the loaded chunks if the chunk the generator puts the surface in is loaded,
+	/// otherwise asks the generator where the surface would be.
+	pub fn get_surface_height(&self, x: i32) -> u32 {
+		let generated = self.chunk_generator.get_surface_height(x);
+		let surface_chunk = WorldPos::checked_new(x as i64, generated as i64);
+		if surface_chunk.is_some_and(|pos| self.chunks.contains_key(pos.get_chunk_pos())) {
+			if let Some(height) = self.heightmap.get(x).and_then(|height| height.tile) {
+				return height;
+			}
+		}
+		generated
+	}
+
+	/// Makes the generator place `schematic` at `origin`. Only chunks that get generated afterwards contain it.
+	pub fn add_structure(&mut self, schematic: Arc<Schematic>, origin: WorldPos) {
+		self.chunk_generator.add_structure(schematic, origin);
+	}
+
+	/// Subscribes to the events of the given kinds. Events queue up in the receiver until they are read.
+	pub fn subscribe(&mut self, kinds: &[EventKind]) -> Receiver<WorldEvent> {
+		self.events.subscribe(kinds)
+	}
+
+	/// Makes the world load chunks from `save` before generating them. Edits the save still had pending
+	/// are queued before the ones made since. The save gets used even if its pending edits can not be read,
+	/// the error only tells that those edits are lost.
+	pub fn set_save(&mut self, save: WorldSave) -> io::Result<()> {
+		let pending = save.load_pending_edits();
+		self.save = Some(save);
+		for (pos, mut edits) in pending? {
+			if let Some(newer) = self.pending_edits.remove(&pos) {
+				edits.extend(newer);
+			}
+			self.pending_edits.insert(pos, edits);
+		}
+		Ok(())
+	}
+
+	/// Writes every loaded chunk and the pending edits to the save. Does nothing if the world has no save.
+	pub fn save(&self) -> io::Result<()> {
+		if let Some(save) = &self.save {
+			for (pos, chunk) in &self.chunks {
+				save.save_chunk(pos, chunk, &self.get_pending_updates(pos))?;
+			}
+			save.save_pending_edits(&self.pending_edits)?;
+			for (id, player) in self.players.iter().enumerate() {
+				save.save_player(id, player)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Loads the player that joins next from the save, if it was saved before.
+	pub fn load_player(&self) -> io::Result<Option<Player>> {
+		match &self.save {
+			Some(save) => save.load_player(self.players.len()),
+			None => Ok(None),
+		}
+	}
+
+	/// The scheduled updates of a chunk with the ticks left until they are due.
+	fn get_pending_updates(&self, pos: &ChunkPos) -> Vec<(ChunkSubPos, u64)> {
+		self.scheduled_updates.get_chunk(pos).into_iter()
+			.map(|(sub_pos, due)| (sub_pos, due.saturating_sub(self.tick_count)))
+			.collect()
+	}
+
+	/// Links the borders of a chunk that is not yet inserted to its loaded neighbors.
+	fn update_borders<C: NeighborAware>(&mut self, pos: &ChunkPos, chunk: &mut Chunk) where Chunk: Grid<C> {
 		for dir in Direction::iter() {
-			pos.shift(dir).map(|neighbor_pos| {
-				self.chunks.get(&neighbor_pos).map(|neighbor| {
-					if dir.is_vertical() {
-						let source = dir.get_y_border();
-						let neigh = dir.flip().get_y_border();
-						for x in 0..CHUNK_SIZE {
-							unsafe {
-								NeighborMatrix::update_neighbor(
-									chunk.get(&ChunkSubPos::new(x as u8, source)),
-									neighbor.get(&ChunkSubPos::new(x as u8, neigh)),
-									dir
-								);
-							}
-						}
-					} else {
-						let source = dir.get_x_border();
-						let neigh = dir.flip().get_x_border();
-						for y in 0..CHUNK_SIZE {
-							unsafe {
-								NeighborMatrix::update_neighbor(
-									chunk.get(&ChunkSubPos::new(source, y as u8)),
-									neighbor.get(&ChunkSubPos::new(neigh, y as u8)),
-									dir
-								);
-							}
-						}
+			if let Some(neighbor) = pos.shift(dir).and_then(|neighbor_pos| self.chunks.get_mut(&neighbor_pos)) {
+				if dir.is_vertical() {
+					let source = dir.get_y_border();
+					let neigh = dir.flip().get_y_border();
+					for x in 0..CHUNK_SIZE {
+						NeighborMatrix::update_neighbor(
+							chunk.get_mut(&ChunkSubPos::new(x as u8, source)),
+							neighbor.get_mut(&ChunkSubPos::new(x as u8, neigh)),
+							dir,
+						);
 					}
-				})
-			});
+				} else {
+					let source = dir.get_x_border();
+					let neigh = dir.flip().get_x_border();
+					for y in 0..CHUNK_SIZE {
+						NeighborMatrix::update_neighbor(
+							chunk.get_mut(&ChunkSubPos::new(source, y as u8)),
+							neighbor.get_mut(&ChunkSubPos::new(neigh, y as u8)),
+							dir,
+						);
+					}
+				}
+			}
 		}
 	}
 
@@ -151,32 +447,600 @@ impl World {
 		})
 	}
 
-	pub fn set<C: NeighborAware>(&mut self, pos: &WorldPos, mut object: C) where Chunk: Grid<C> {
-		return if self.chunks.contains_key(pos.get_chunk_pos()) {
+	/// Sets the object at `pos`. If the chunk is not loaded the change gets applied once it is.
+	pub fn set<C: Layer>(&mut self, pos: &WorldPos, object: C) where Chunk: Grid<C> {
+		if self.set_internal(pos, object) {
+			self.update_support(pos);
+			self.update_falling(pos);
+		} else {
+			self.defer(pos, object);
+		}
+	}
+
+	/// Breaks every tile around `pos` that lost its support. Broken tiles can make their neighbors break too.
+	fn update_support(&mut self, pos: &WorldPos) {
+		let mut queue = VecDeque::new();
+		queue.push_back(*pos);
+		for dir in Direction::iter() {
+			if let Some(neighbor) = pos.shift(dir) { queue.push_back(neighbor); }
+		}
+
+		while let Some(pos) = queue.pop_front() {
+			let tile = match self.get::<Tile>(&pos) {
+				Some(tile) if tile.id != tile::AIR => *tile,
+				_ => continue,
+			};
+
+			let supported = match ObjectType::get(&tile.id) {
+				Some(object) => match ObjectType::get_origin(&tile, &pos) {
+					Some(origin) => object.is_complete(self, &origin) && object.is_anchored(self, &origin),
+					None => false,
+				},
+				None => tile.id.get_support_rule().is_satisfied(self, &pos),
+			};
+
+			if !supported {
+				for removed in self.remove_tile(&pos) {
+					for dir in Direction::iter() {
+						if let Some(neighbor) = removed.shift(dir) { queue.push_back(neighbor); }
+					}
+				}
+			}
+		}
+	}
+
+	/// Breaks the tile at `pos` and drops it. Breaking a part of a multi tile object breaks the whole object.
+	/// Returns false if there was nothing to break.
+	pub fn break_tile(&mut self, pos: &WorldPos) -> bool {
+		let removed = self.remove_tile(pos);
+		for pos in &removed {
+			self.update_support(pos);
+		}
+		!removed.is_empty()
+	}
+
+	/// Replaces the tile (or the object it is part of) with air and drops it once. Returns every cleared position.
+	fn remove_tile(&mut self, pos: &WorldPos) -> Vec<WorldPos> {
+		let tile = match self.get::<Tile>(pos) {
+			Some(tile) if tile.id != tile::AIR => *tile,
+			_ => return Vec::new(),
+		};
+
+		// A part whose object does not fit into the world gets removed on its own.
+		let object = ObjectType::get(&tile.id).and_then(|object| {
+			let origin = ObjectType::get_origin(&tile, pos)?;
+			Some((origin, object.get_footprint(&origin)?))
+		});
+		let (origin, parts) = match object {
+			Some((origin, footprint)) => {
+				let parts = footprint.into_iter()
+					.filter(|(part_pos, frame_x, frame_y)| {
+						matches!(self.get::<Tile>(part_pos), Some(part) if part.id == tile.id && part.frame_x == *frame_x && part.frame_y == *frame_y)
+					})
+					.map(|(part_pos, _, _)| part_pos)
+					.collect();
+				(origin, parts)
+			}
+			None => (*pos, vec![*pos]),
+		};
+
+		for part in &parts {
+			self.set_internal(part, Tile::air());
+		}
+		for part in &parts {
+			self.update_falling(part);
+		}
+		if let Some(id) = ItemId::from_tile_drop(&tile.id) {
+			self.drops.push(ItemDrop { pos: origin, stack: ItemStack::new(id, 1) });
+		}
+		parts
+	}
+
+	/// Places the multi tile object of `id` with its bottom left part at `origin`.
+	/// The whole footprint needs to be loaded and empty, and the object anchor needs to be satisfied.
+	pub fn place_object(&mut self, origin: &WorldPos, id: TileId) -> Result<(), PlaceError> {
+		let object = ObjectType::get(&id).ok_or(PlaceError::NotAnObject)?;
+		let footprint = object.get_footprint(origin).ok_or(PlaceError::OutOfWorld)?;
+		for (pos, _, _) in &footprint {
+			match self.get::<Tile>(pos) {
+				None => return Err(PlaceError::NotLoaded(*pos)),
+				Some(tile) if tile.id != tile::AIR => return Err(PlaceError::Obstructed(*pos)),
+				Some(_) => {}
+			}
+		}
+
+		if !object.is_anchored(self, origin) {
+			return Err(PlaceError::NoAnchor);
+		}
+
+		for (pos, frame_x, frame_y) in &footprint {
+			self.set_internal(pos, Tile::part(id, *frame_x, *frame_y));
+		}
+		Ok(())
+	}
+
+	/// The items lying in the world, oldest first. Players pick them up once they get close.
+	pub fn get_drops(&self) -> &[ItemDrop] {
+		&self.drops
+	}
+
+	/// Places the object and updates its neighbors without triggering any world reactions.
+	fn set_internal<C: Layer>(&mut self, pos: &WorldPos, mut object: C) -> bool where Chunk: Grid<C> {
+		if let Some(old) = self.get::<C>(pos).copied() {
+			self.record(pos, old);
 			self.update_neighbor(pos, &mut object);
 
 			let chunk = self.get_chunk_mut(pos.get_chunk_pos()).unwrap();
 			chunk.set(pos.get_chunk_sub_pos(), object);
-			self.chunk_updates.insert(*pos.get_chunk_pos());
+			let removed = chunk.sync_tile_entity(pos.get_chunk_sub_pos());
+			self.drop_contents(pos, removed);
+			self.heightmap.update(pos, &self.chunks);
+			self.events.emit(C::create_event(*pos, old, object));
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Makes the tiles at and above `pos` fall if they have nothing below them anymore.
+	/// Walks up the column so a whole pillar of sand starts falling at once.
+	fn update_falling(&mut self, pos: &WorldPos) {
+		let mut pos = *pos;
+		let mut first = true;
+		loop {
+			let tile = match self.get::<Tile>(&pos) {
+				Some(tile) => *tile,
+				None => return,
+			};
+
+			let supported = match pos.shift(Direction::Down) {
+				Some(below) => !matches!(self.get::<Tile>(&below), Some(below) if below.id == tile::AIR),
+				None => true,
+			};
+
+			if tile.id.has_gravity() && !supported {
+				self.set_internal(&pos, Tile::air());
+				let mut falling = FallingTile::new(tile, &pos);
+				falling.action = self.history.get_target();
+				self.falling_tiles.push(falling);
+			} else if !first {
+				return;
+			}
+
+			first = false;
+			match pos.shift(Direction::Top) {
+				Some(above) => pos = above,
+				None => return,
+			}
+		}
+	}
+
+	/// Dispatches random ticks to the tiles of every loaded chunk. Chunks get visited in a fixed order so a seed always gives the same result.
+	fn random_tick(&mut self) {
+		let mut chunks: Vec<ChunkPos> = self.chunks.keys().copied().collect();
+		chunks.sort_by_key(|pos| (pos.x, pos.y));
+
+		let mut random = self.random_ticker.random;
+		for chunk_pos in chunks {
+			for _ in 0..self.random_ticker.ticks_per_chunk {
+				let x = random.next_range(CHUNK_SIZE as u32) as u8;
+				let y = random.next_range(CHUNK_SIZE as u32) as u8;
+				let pos = WorldPos::from_chunk(&chunk_pos, x, y);
+				if let Some(handler) = self.get::<Tile>(&pos).and_then(|tile| self.random_ticker.get_handler(&tile.id)) {
+					handler(self, &pos, &mut random);
+				}
+			}
+		}
+		self.random_ticker.random = random;
+	}
+
+	/// Updates the tile at `pos` again in `delay` ticks. If the position already has an earlier update queued, that one wins.
+	pub fn schedule_update(&mut self, pos: &WorldPos, delay: u64) -> bool {
+		self.scheduled_updates.schedule(pos, self.tick_count + delay.max(1))
+	}
+
+	fn tick_scheduled_updates(&mut self) {
+		let chunks = &self.chunks;
+		for pos in self.scheduled_updates.take_due(self.tick_count, |chunk| chunks.contains_key(chunk)) {
+			self.update_support(&pos);
+			self.update_falling(&pos);
+			if let Some(handler) = self.get::<Tile>(&pos).and_then(|tile| self.scheduled_updates.get_handler(&tile.id)) {
+				handler(self, &pos);
+			}
+		}
+	}
+
+	pub fn get_scheduled_updates(&self) -> &ScheduledUpdates {
+		&self.scheduled_updates
+	}
+
+	pub fn get_scheduled_updates_mut(&mut self) -> &mut ScheduledUpdates {
+		&mut self.scheduled_updates
+	}
+
+	pub fn get_tick_count(&self) -> u64 {
+		self.tick_count
+	}
+
+	pub fn get_random_ticker_mut(&mut self) -> &mut RandomTicker {
+		&mut self.random_ticker
+	}
+
+	fn tick_falling_tiles(&mut self) {
+		let mut falling_tiles = std::mem::take(&mut self.falling_tiles);
+		falling_tiles.retain_mut(|falling| match falling.tick(self) {
+			FallResult::Falling => true,
+			FallResult::Landed(y) => {
+				self.land(falling, y);
+				false
+			}
+		});
+		// Tiles that started falling while others landed got added to the now empty list.
+		falling_tiles.append(&mut self.falling_tiles);
+		self.falling_tiles = falling_tiles;
+	}
+
+	fn land(&mut self, falling: &FallingTile, mut y: u32) {
+		// Another tile may have landed in the same spot this tick.
+		loop {
+			let pos = match WorldPos::checked_new(falling.x as i64, y as i64) {
+				Some(pos) => pos,
+				// The column is full up to the top of the world, so the tile drops as an item instead.
+				None => {
+					if let Some(id) = ItemId::from_tile_drop(&falling.tile.id) {
+						self.drops.push(ItemDrop { pos: WorldPos::new(falling.x, WorldPos::MAX_Y), stack: ItemStack::new(id, 1) });
+					}
+					return;
+				}
+			};
+			match self.get::<Tile>(&pos) {
+				Some(tile) if tile.id != tile::AIR => y += 1,
+				// An unloaded chunk gets the tile once it loads.
+				_ => {
+					// The landing belongs to the action that made the tile fall.
+					let target = self.history.set_target(falling.action);
+					self.set(&pos, falling.tile);
+					self.history.set_target(target);
+					return;
+				}
+			}
+		}
+	}
+
+	pub fn get_falling_tiles(&self) -> &[FallingTile] {
+		&self.falling_tiles
+	}
+
+
+	/// Cycles the shape of the tile at `pos` like a hammer would. Returns false if there is nothing to hammer.
+	pub fn hammer(&mut self, pos: &WorldPos) -> bool {
+		match self.get::<Tile>(pos) {
+			Some(tile) if tile.id != tile::AIR => {
+				let mut tile = *tile;
+				tile.shape = tile.shape.hammer();
+				self.set(pos, tile);
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Paints the object at `pos`. Air cannot be painted and paints outside the palette are rejected.
+	/// Returns false if nothing changed.
+	pub fn paint<C: Layer + Paintable>(&mut self, pos: &WorldPos, paint: PaintId) -> bool where Chunk: Grid<C> {
+		if paint != paint::NONE && self.paints.get(&paint).is_none() {
+			return false;
+		}
+
+		let mut object = match self.get::<C>(pos) {
+			Some(object) if object.can_paint() && object.get_paint() != paint => *object,
+			_ => return false,
 		};
+		object.set_paint(paint);
+		self.set_internal(pos, object)
+	}
+
+	/// Removes the paint of the object at `pos`.
+	pub fn scrape<C: Layer + Paintable>(&mut self, pos: &WorldPos) -> bool where Chunk: Grid<C> {
+		self.paint::<C>(pos, paint::NONE)
+	}
+
+	/// Applies every change of the batch, recalculates the neighbors of the touched area once
+	/// and emits a single `ChunkEdited` event for every chunk that changed instead of an event per position.
+	/// Returns the amount of applied changes.
+	pub fn apply_edit(&mut self, edit: WorldEdit) -> usize {
+		let mut changed = Vec::new();
+		for (pos, entity) in self.write_edit(edit, &mut changed) {
+			self.drop_contents(&pos, Some(entity));
+		}
+		self.refresh_area(&changed);
+
+		for pos in &changed {
+			self.update_support(pos);
+			self.update_falling(pos);
+		}
+		changed.len()
+	}
+
+	/// Writes every change of the batch and returns the tile entities that got replaced.
+	fn write_edit(&mut self, edit: WorldEdit, changed: &mut Vec<WorldPos>) -> Vec<(WorldPos, TileEntity)> {
+		let mut removed = Vec::new();
+		self.apply_layer(edit.tiles, changed, &mut removed);
+		self.apply_layer(edit.walls, changed, &mut removed);
+		self.apply_layer(edit.liquids, changed, &mut removed);
+		removed
+	}
+
+	/// Recalculates the neighbors around the changed positions and emits a `ChunkEdited` event for every touched chunk.
+	fn refresh_area(&mut self, changed: &[WorldPos]) {
+		let mut affected = HashSet::new();
+		for pos in changed {
+			affected.insert(*pos);
+			for dir in Direction::iter() {
+				if let Some(neighbor) = pos.shift(dir) { affected.insert(neighbor); }
+			}
+		}
+
+		let mut chunks = HashSet::new();
+		for pos in &affected {
+			self.refresh_neighbors::<Tile>(pos);
+			self.refresh_neighbors::<Wall>(pos);
+			self.refresh_neighbors::<Liquid>(pos);
+			chunks.insert(*pos.get_chunk_pos());
+		}
+
+		for chunk in chunks {
+			if self.chunks.contains_key(&chunk) {
+				self.events.emit(WorldEvent::ChunkEdited(chunk));
+			}
+		}
+	}
+
+	/// Sets every position of the region to `object`. Returns the amount of changed positions.
+	pub fn fill<C: Layer>(&mut self, region: &Region, object: C) -> usize where Chunk: Grid<C> {
+		let mut edit = WorldEdit::new();
+		for pos in region.iter() {
+			edit.set(&pos, object);
+		}
+		self.apply_edit(edit)
+	}
+
+	/// Turns every loaded tile of type `from` in the region into `to`, keeping its shape and paint.
+	pub fn replace_tiles(&mut self, region: &Region, from: TileId, to: TileId) -> usize {
+		let mut edit = WorldEdit::new();
+		for pos in region.iter() {
+			if let Some(tile) = self.get::<Tile>(&pos).filter(|tile| tile.id == from) {
+				let mut tile = *tile;
+				tile.id = to;
+				edit.set(&pos, tile);
+			}
+		}
+		self.apply_edit(edit)
+	}
+
+	/// Turns every loaded wall of type `from` in the region into `to`, keeping its paint.
+	pub fn replace_walls(&mut self, region: &Region, from: WallId, to: WallId) -> usize {
+		let mut edit = WorldEdit::new();
+		for pos in region.iter() {
+			if let Some(wall) = self.get::<Wall>(&pos).filter(|wall| wall.id == from) {
+				let mut wall = *wall;
+				wall.id = to;
+				edit.set(&pos, wall);
+			}
+		}
+		self.apply_edit(edit)
+	}
+
+	/// Copies the tiles, walls, liquids and tile entities of the region.
+	pub fn copy(&self, region: &Region) -> Clipboard {
+		let min = region.get_min();
+		let mut clipboard = Clipboard::new(region.get_width(), region.get_height());
+		for pos in region.iter() {
+			let x = (pos.get_x() as i64 - min.get_x() as i64) as u32;
+			let y = pos.get_y() - min.get_y();
+			if let Some(chunk) = self.get_chunk(pos.get_chunk_pos()) {
+				let sub_pos = pos.get_chunk_sub_pos();
+				clipboard.set(x, y, Some(ClipboardCell {
+					tile: *chunk.get(sub_pos),
+					wall: *chunk.get(sub_pos),
+					liquid: *chunk.get(sub_pos),
+				}));
+				if let Some(entity) = chunk.get_tile_entity(sub_pos) {
+					clipboard.set_tile_entity(x, y, entity.clone());
+				}
+			}
+		}
+		clipboard
+	}
+
+	/// Pastes the clipboard with its bottom left corner at `origin` after applying `transform`.
+	/// Returns the amount of applied changes, counting every layer of a position.
+	pub fn paste(&mut self, clipboard: &Clipboard, origin: &WorldPos, transform: &Transform) -> usize {
+		let clipboard = clipboard.transform(transform);
+		// Cells that end up outside of the world are left out.
+		let get_pos = |x: u32, y: u32| WorldPos::checked_new(origin.get_x() as i64 + x as i64, origin.get_y() as i64 + y as i64);
+
+		let mut edit = WorldEdit::new();
+		for y in 0..clipboard.get_height() {
+			for x in 0..clipboard.get_width() {
+				if let (Some(cell), Some(pos)) = (clipboard.get(x, y), get_pos(x, y)) {
+					edit.set(&pos, cell.tile);
+					edit.set(&pos, cell.wall);
+					edit.set(&pos, cell.liquid);
+				}
+			}
+		}
+		let changed = self.apply_edit(edit);
+
+		for ((x, y), entity) in clipboard.get_tile_entities() {
+			if let Some(target) = get_pos(*x, *y).and_then(|pos| self.get_tile_entity_mut(&pos)) {
+				*target = entity.clone();
+			}
+		}
+		changed
+	}
+
+	/// Writes the objects straight into their chunks without touching neighbors. Unloaded chunks get them once they load.
+	fn apply_layer<C: Layer>(&mut self, objects: HashMap<WorldPos, C>, changed: &mut Vec<WorldPos>, removed: &mut Vec<(WorldPos, TileEntity)>) where Chunk: Grid<C> {
+		for (pos, object) in objects {
+			if let Some(old) = self.get::<C>(&pos).copied() {
+				self.record(&pos, old);
+				let chunk = self.get_chunk_mut(pos.get_chunk_pos()).unwrap();
+				chunk.set(pos.get_chunk_sub_pos(), object);
+				if let Some(entity) = chunk.sync_tile_entity(pos.get_chunk_sub_pos()) {
+					removed.push((pos, entity));
+				}
+				self.heightmap.update(&pos, &self.chunks);
+				changed.push(pos);
+			} else {
+				self.defer(&pos, object);
+			}
+		}
+	}
+
+	/// Drops the contents of an entity whose tile got replaced, so nothing that was stored in a chest gets lost.
+	fn drop_contents(&mut self, pos: &WorldPos, entity: Option<TileEntity>) {
+		if let Some(TileEntity::Chest(chest)) = entity {
+			for stack in chest.inventory.get_slots().iter().flatten() {
+				self.drops.push(ItemDrop { pos: *pos, stack: *stack });
+			}
+		}
+	}
+
+	/// Adds the state of `pos` before a change to the open history action.
+	fn record<C: Layer>(&mut self, pos: &WorldPos, old: C) where Chunk: Grid<C> {
+		if self.history.get_target().is_some() {
+			let entity = self.get_tile_entity(pos).cloned();
+			self.history.record(pos, old, entity);
+		}
+	}
+
+	/// Starts a new undoable action. Every change until `end_action` gets undone together, except what players mine and place.
+	pub fn begin_action(&mut self) {
+		self.history.begin_action();
+	}
+
+	pub fn end_action(&mut self) {
+		self.history.end_action();
+	}
+
+	/// Reverts the last action. Returns false if there was nothing to undo.
+	pub fn undo(&mut self) -> bool {
+		self.history.end_action();
+		if let Some(id) = self.history.get_undo_id() {
+			self.settle_falling_tiles(id);
+		}
+		match self.history.pop_undo() {
+			Some(action) => {
+				let inverse = self.restore(action);
+				self.history.push_redo(inverse);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Applies the last undone action again. Returns false if there was nothing to redo.
+	pub fn redo(&mut self) -> bool {
+		self.history.end_action();
+		match self.history.pop_redo() {
+			Some(action) => {
+				let inverse = self.restore(action);
+				self.history.push_undo(inverse);
+				true
+			}
+			None => false,
+		}
+	}
+
+	pub fn get_history(&self) -> &History {
+		&self.history
 	}
 
+	pub fn get_history_mut(&mut self) -> &mut History {
+		&mut self.history
+	}
+
+	/// Lands the tiles that are still falling because of the action `id` right away, so the action knows where they end up.
+	fn settle_falling_tiles(&mut self, id: u64) {
+		let (settling, falling): (Vec<FallingTile>, Vec<FallingTile>) = std::mem::take(&mut self.falling_tiles)
+			.into_iter()
+			.partition(|falling| falling.action == Some(id));
+		self.falling_tiles = falling;
+		for falling in settling {
+			let mut y = falling.y.floor().max(0.0) as u32;
+			while y > 0 && matches!(self.get::<Tile>(&WorldPos::new(falling.x, y - 1)), Some(below) if below.id == tile::AIR) {
+				y -= 1;
+			}
+			self.land(&falling, y);
+		}
+	}
+
+	/// Puts back the state stored in `action` and returns the action that reverts it again.
+	/// Nothing breaks, falls or drops while restoring, the action already holds the state those reactions left behind.
+	fn restore(&mut self, action: Action) -> Action {
+		let mut inverse = self.history.create_action();
+		self.capture_inverse::<Tile>(&action, &mut inverse);
+		self.capture_inverse::<Wall>(&action, &mut inverse);
+		self.capture_inverse::<Liquid>(&action, &mut inverse);
+
+		let mut changed = Vec::new();
+		// Replaced entities are part of the inverse action, dropping their contents would duplicate them.
+		let _ = self.write_edit(action.edit, &mut changed);
+		self.refresh_area(&changed);
+		for (pos, entity) in action.tile_entities {
+			if let Some(target) = self.get_tile_entity_mut(&pos) {
+				*target = entity;
+			}
+		}
+		inverse
+	}
+
+	fn capture_inverse<C: Layer>(&self, action: &Action, inverse: &mut Action) where Chunk: Grid<C> {
+		for pos in C::get_edits(&action.edit).keys() {
+			if let Some(current) = self.get::<C>(pos) {
+				inverse.record(pos, *current, self.get_tile_entity(pos).cloned());
+			}
+		}
+	}
+
+	/// Recalculates the neighbor matrix of the object at `pos` from its current neighbors. Only `pos` itself gets modified.
+	fn refresh_neighbors<C: Layer>(&mut self, pos: &WorldPos) where Chunk: Grid<C> {
+		let object = match self.get::<C>(pos) {
+			Some(object) => *object,
+			None => return,
+		};
+
+		let mut matrix = *object.get_neighbor_matrix();
+		for dir in Direction::iter() {
+			if let Some(neighbor) = pos.shift(dir).and_then(|neighbor_pos| self.get::<C>(&neighbor_pos)) {
+				matrix.set_neighbor_type(dir, object.apply_neighbor(neighbor, dir));
+			}
+		}
+
+		if let Some(object) = self.get_mut::<C>(pos) {
+			*object.get_neighbor_matrix_mut() = matrix;
+		}
+	}
 
 	fn update_neighbor<C: NeighborAware>(&mut self, pos: &WorldPos, object: &mut C) where Chunk: Grid<C> {
 		for i in Direction::iter() {
 			if let Some(neighbor_pos) = pos.shift(i) {
-				self.chunk_updates.insert(*neighbor_pos.get_chunk_pos());
-
 				if let Some(neighbor) = self.get_mut(&neighbor_pos) {
-					unsafe {
-						// Mutates the values!!!
-						NeighborMatrix::update_neighbor(object, neighbor, i);
-					}
+					NeighborMatrix::update_neighbor(object, neighbor, i);
 				}
 			}
 		}
 	}
 
+	pub fn get_tile_entity(&self, pos: &WorldPos) -> Option<&TileEntity> {
+		self.get_chunk(pos.get_chunk_pos()).and_then(|chunk| chunk.get_tile_entity(pos.get_chunk_sub_pos()))
+	}
+
+	pub fn get_tile_entity_mut(&mut self, pos: &WorldPos) -> Option<&mut TileEntity> {
+		self.get_chunk_mut(pos.get_chunk_pos()).and_then(|chunk| chunk.get_tile_entity_mut(pos.get_chunk_sub_pos()))
+	}
+
 	pub fn get_chunk_mut(&mut self, pos: &ChunkPos) -> Option<&mut Chunk> {
 		if self.chunks.contains_key(pos) {
 			Some(self.chunks.get_mut(pos).unwrap())
@@ -198,16 +1062,67 @@ impl World {
 pub struct Chunk {
 	solid_tiles: [[Tile; CHUNK_SIZE]; CHUNK_SIZE],
 	solid_walls: [[Wall; CHUNK_SIZE]; CHUNK_SIZE],
+	liquids: [[Liquid; CHUNK_SIZE]; CHUNK_SIZE],
+	tile_entities: HashMap<ChunkSubPos, TileEntity>,
 }
 
 impl Default for Chunk {
 	fn default() -> Self {
 		let solid_tiles = [[Tile::air(); CHUNK_SIZE]; CHUNK_SIZE];
 		let solid_walls = [[Wall::air(); CHUNK_SIZE]; CHUNK_SIZE];
+		let liquids = [[Liquid::air(); CHUNK_SIZE]; CHUNK_SIZE];
 
 		Self {
 			solid_tiles,
 			solid_walls,
+			liquids,
+			tile_entities: HashMap::new(),
+		}
+	}
+}
+
+impl Chunk {
+	pub fn get_tile_entity(&self, pos: &ChunkSubPos) -> Option<&TileEntity> {
+		self.tile_entities.get(pos)
+	}
+
+	pub fn get_tile_entity_mut(&mut self, pos: &ChunkSubPos) -> Option<&mut TileEntity> {
+		self.tile_entities.get_mut(pos)
+	}
+
+	/// Creates or removes the tile entity at `pos` so it matches the tile that is there now.
+	/// Multi tile objects only keep their entity in the bottom left part. Returns the entity that got removed.
+	#[must_use]
+	fn sync_tile_entity(&mut self, pos: &ChunkSubPos) -> Option<TileEntity> {
+		let tile = self.solid_tiles[pos.y as usize][pos.x as usize];
+		let id = if tile.frame_x == 0 && tile.frame_y == 0 { tile.id } else { tile::AIR };
+		match self.tile_entities.get(pos) {
+			Some(entity) if entity.is_owned_by(&id) => None,
+			_ => {
+				let removed = self.tile_entities.remove(pos);
+				if let Some(entity) = TileEntity::create(&id) {
+					self.tile_entities.insert(*pos, entity);
+				}
+				removed
+			}
+		}
+	}
+
+	#[must_use]
+	fn apply_pending_edit(&mut self, edit: &PendingEdit) -> Option<TileEntity> {
+		match *edit {
+			PendingEdit::Tile(pos, tile) => self.set(&pos, tile),
+			PendingEdit::Wall(pos, wall) => self.set(&pos, wall),
+			PendingEdit::Liquid(pos, liquid) => self.set(&pos, liquid),
+		}
+		self.sync_tile_entity(edit.get_pos())
+	}
+
+	fn tick_tile_entities(&mut self) {
+		for entity in self.tile_entities.values_mut() {
+			if entity.needs_tick() {
+				entity.tick();
+			}
 		}
 	}
 }
@@ -242,9 +1157,75 @@ macro_rules! grid {
 
 grid!(
 	Tile => solid_tiles,
-	Wall => solid_walls
+	Wall => solid_walls,
+	Liquid => liquids
 );
 
+/// One of the grids every chunk holds.
+pub trait Layer: NeighborAware + Copy {
+	fn create_event(pos: WorldPos, old: Self, new: Self) -> WorldEvent;
+
+	fn get_edits(edit: &WorldEdit) -> &HashMap<WorldPos, Self>;
+
+	fn get_edits_mut(edit: &mut WorldEdit) -> &mut HashMap<WorldPos, Self>;
+
+	fn create_pending(pos: ChunkSubPos, object: Self) -> PendingEdit;
+}
+
+impl Layer for Tile {
+	fn create_event(pos: WorldPos, old: Self, new: Self) -> WorldEvent {
+		WorldEvent::TileChanged { pos, old, new }
+	}
+
+	fn get_edits(edit: &WorldEdit) -> &HashMap<WorldPos, Self> {
+		&edit.tiles
+	}
+
+	fn get_edits_mut(edit: &mut WorldEdit) -> &mut HashMap<WorldPos, Self> {
+		&mut edit.tiles
+	}
+
+	fn create_pending(pos: ChunkSubPos, object: Self) -> PendingEdit {
+		PendingEdit::Tile(pos, object)
+	}
+}
+
+impl Layer for Wall {
+	fn create_event(pos: WorldPos, old: Self, new: Self) -> WorldEvent {
+		WorldEvent::WallChanged { pos, old, new }
+	}
+
+	fn get_edits(edit: &WorldEdit) -> &HashMap<WorldPos, Self> {
+		&edit.walls
+	}
+
+	fn get_edits_mut(edit: &mut WorldEdit) -> &mut HashMap<WorldPos, Self> {
+		&mut edit.walls
+	}
+
+	fn create_pending(pos: ChunkSubPos, object: Self) -> PendingEdit {
+		PendingEdit::Wall(pos, object)
+	}
+}
+
+impl Layer for Liquid {
+	fn create_event(pos: WorldPos, old: Self, new: Self) -> WorldEvent {
+		WorldEvent::LiquidChanged { pos, old, new }
+	}
+
+	fn get_edits(edit: &WorldEdit) -> &HashMap<WorldPos, Self> {
+		&edit.liquids
+	}
+
+	fn get_edits_mut(edit: &mut WorldEdit) -> &mut HashMap<WorldPos, Self> {
+		&mut edit.liquids
+	}
+
+	fn create_pending(pos: ChunkSubPos, object: Self) -> PendingEdit {
+		PendingEdit::Liquid(pos, object)
+	}
+}
+
 pub trait Grid<C> {
 	fn get_mut(&mut self, pos: &ChunkSubPos) -> &mut C;
 	fn get(&self, pos: &ChunkSubPos) -> &C;
@@ -1,12 +1,15 @@
-use crate::misc::util::Direction;
+use crate::misc::util::{CHUNK_SIZE, Direction};
 
 pub trait NeighborAware {
 	fn get_neighbor_matrix(&self) -> &NeighborMatrix;
 
-	fn apply_neighbor(&self, neighbor: &Self) -> NeighborType;
+	fn get_neighbor_matrix_mut(&mut self) -> &mut NeighborMatrix;
+
+	/// Calculates how this object connects to `neighbor` which is located in `direction` of it.
+	fn apply_neighbor(&self, neighbor: &Self, direction: Direction) -> NeighborType;
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct NeighborMatrix {
 	top: NeighborType,
 	down: NeighborType,
@@ -44,23 +47,149 @@ impl NeighborMatrix {
 		}
 	}
 
-	/// Actually mutates the values. watch out!
-	/// # Safety idk
-	pub unsafe fn update_neighbor<C: NeighborAware>(source: &C, neighbor: &C, direction: Direction) {
+	/// Updates both sides of a neighbor relationship. `neighbor` is the object in `direction` of `source`.
+	pub fn update_neighbor<C: NeighborAware>(source: &mut C, neighbor: &mut C, direction: Direction) {
+		let source_type = source.apply_neighbor(neighbor, direction);
+		let neighbor_type = neighbor.apply_neighbor(source, direction.flip());
+		source.get_neighbor_matrix_mut().set_neighbor_type(direction, source_type);
+		neighbor.get_neighbor_matrix_mut().set_neighbor_type(direction.flip(), neighbor_type);
+	}
 
-		unsafe {
-			let holder_ptr = source.get_neighbor_matrix() as *const NeighborMatrix as *mut NeighborMatrix;
-			let neighbor_ptr = neighbor.get_neighbor_matrix() as *const NeighborMatrix as *mut NeighborMatrix;
+	/// Recalculates every neighbor relationship inside of a grid.
+	/// Objects on the edges get their outward sides left untouched as those belong to other grids.
+	pub fn update_grid<C: NeighborAware>(grid: &mut [[C; CHUNK_SIZE]; CHUNK_SIZE]) {
+		// Horizontal pairs
+		for row in grid.iter_mut() {
+			for x in 0..(CHUNK_SIZE - 1) {
+				let (left, right) = row.split_at_mut(x + 1);
+				Self::update_neighbor(&mut left[x], &mut right[0], Direction::Right);
+			}
+		}
 
-			(holder_ptr.as_mut().unwrap()).set_neighbor_type(direction, source.apply_neighbor(neighbor));
-			(neighbor_ptr.as_mut().unwrap()).set_neighbor_type(direction.flip(), neighbor.apply_neighbor(source));
+		// Vertical pairs
+		for y in 0..(CHUNK_SIZE - 1) {
+			let (below, above) = grid.split_at_mut(y + 1);
+			for x in 0..CHUNK_SIZE {
+				Self::update_neighbor(&mut below[y][x], &mut above[0][x], Direction::Top);
+			}
 		}
 	}
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum NeighborType {
 	Air,
 	Same,
 	Transitional,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::misc::pos::{ChunkPos, ChunkSubPos, WorldPos};
+	use crate::world::{Chunk, Grid, tile};
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile::{Tile, TileShape};
+
+	/// Stone with holes, half blocks and slopes so every kind of connection shows up.
+	fn create_grid() -> [[Tile; CHUNK_SIZE]; CHUNK_SIZE] {
+		let mut grid = [[Tile::air(); CHUNK_SIZE]; CHUNK_SIZE];
+		for (y, row) in grid.iter_mut().enumerate() {
+			for (x, object) in row.iter_mut().enumerate() {
+				if (x * 7 + y * 3) % 5 != 0 {
+					*object = Tile::id(tile::STONE);
+					object.shape = match (x + y) % 11 {
+						0 => TileShape::Half,
+						1 => TileShape::SlopeBottomRight,
+						2 => TileShape::SlopeTopLeft,
+						_ => TileShape::Full,
+					};
+				}
+			}
+		}
+		grid
+	}
+
+	/// The matrix of every object worked out one neighbor at a time. Sides facing out of the grid stay air.
+	fn get_expected(grid: &[[Tile; CHUNK_SIZE]; CHUNK_SIZE]) -> Vec<Vec<NeighborMatrix>> {
+		(0..CHUNK_SIZE).map(|y| (0..CHUNK_SIZE).map(|x| {
+			let mut matrix = NeighborMatrix::default();
+			for direction in Direction::iter() {
+				let neighbor_x = x as i32 + direction.get_x_difference() as i32;
+				let neighbor_y = y as i32 + direction.get_y_difference() as i32;
+				if (0..CHUNK_SIZE as i32).contains(&neighbor_x) && (0..CHUNK_SIZE as i32).contains(&neighbor_y) {
+					let neighbor = &grid[neighbor_y as usize][neighbor_x as usize];
+					matrix.set_neighbor_type(direction, grid[y][x].apply_neighbor(neighbor, direction));
+				}
+			}
+			matrix
+		}).collect()).collect()
+	}
+
+	#[test]
+	fn update_grid_matches_pairwise_neighbors() {
+		let mut grid = create_grid();
+		let expected = get_expected(&grid);
+		NeighborMatrix::update_grid(&mut grid);
+		for y in 0..CHUNK_SIZE {
+			for x in 0..CHUNK_SIZE {
+				assert_eq!(grid[y][x].matrix, expected[y][x], "Tile at {} {}", x, y);
+			}
+		}
+	}
+
+	#[test]
+	fn update_neighbor_sets_both_sides() {
+		let mut source = Tile::id(tile::STONE);
+		let mut neighbor = Tile::id(tile::DIRT);
+		NeighborMatrix::update_neighbor(&mut source, &mut neighbor, Direction::Right);
+		assert_eq!(source.matrix.get_neighbor_type(Direction::Right), NeighborType::Same);
+		assert_eq!(neighbor.matrix.get_neighbor_type(Direction::Left), NeighborType::Same);
+		assert_eq!(source.matrix.get_neighbor_type(Direction::Left), NeighborType::Air);
+
+		neighbor = Tile::air();
+		NeighborMatrix::update_neighbor(&mut source, &mut neighbor, Direction::Right);
+		assert_eq!(source.matrix.get_neighbor_type(Direction::Right), NeighborType::Air);
+	}
+
+	#[test]
+	fn chunk_borders_connect_when_loaded() {
+		let stone = || create_chunk(|_, _| tile::STONE);
+		let world = create_world(vec![(ChunkPos::new(0, 0), stone()), (ChunkPos::new(1, 0), stone()), (ChunkPos::new(0, 1), stone())]);
+		let last = (CHUNK_SIZE - 1) as u8;
+		for y in 0..CHUNK_SIZE as u8 {
+			let left: &Tile = world.get(&WorldPos::from_chunk(&ChunkPos::new(0, 0), last, y)).unwrap();
+			let right: &Tile = world.get(&WorldPos::from_chunk(&ChunkPos::new(1, 0), 0, y)).unwrap();
+			assert_eq!(left.matrix.get_neighbor_type(Direction::Right), NeighborType::Same);
+			assert_eq!(right.matrix.get_neighbor_type(Direction::Left), NeighborType::Same);
+			// Chunk -1 0 is not loaded.
+			let edge: &Tile = world.get(&WorldPos::from_chunk(&ChunkPos::new(0, 0), 0, y)).unwrap();
+			assert_eq!(edge.matrix.get_neighbor_type(Direction::Left), NeighborType::Air);
+		}
+		for x in 0..CHUNK_SIZE as u8 {
+			let below: &Tile = world.get(&WorldPos::from_chunk(&ChunkPos::new(0, 0), x, last)).unwrap();
+			let above: &Tile = world.get(&WorldPos::from_chunk(&ChunkPos::new(0, 1), x, 0)).unwrap();
+			assert_eq!(below.matrix.get_neighbor_type(Direction::Top), NeighborType::Same);
+			assert_eq!(above.matrix.get_neighbor_type(Direction::Down), NeighborType::Same);
+		}
+	}
+
+	#[test]
+	fn setting_a_tile_updates_its_neighbors() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, _| tile::STONE))]);
+		let pos = WorldPos::new(5, 5);
+		world.set(&pos, Tile::air());
+		for (direction, neighbor) in pos.get_neighbors() {
+			let neighbor: &Tile = world.get(&neighbor).unwrap();
+			assert_eq!(neighbor.matrix.get_neighbor_type(direction.flip()), NeighborType::Air);
+		}
+
+		let chunk: &Chunk = world.get_chunk(&ChunkPos::new(0, 0)).unwrap();
+		let expected = get_expected(Grid::<Tile>::get_grid(chunk));
+		for sub_pos in ChunkSubPos::iter() {
+			// The outer sides have no loaded neighbors, so they stay air like in the pairwise result.
+			let object: &Tile = chunk.get(&sub_pos);
+			assert_eq!(object.matrix, expected[sub_pos.y as usize][sub_pos.x as usize]);
+		}
+	}
+}
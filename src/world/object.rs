@@ -0,0 +1,237 @@
+use crate::misc::pos::WorldPos;
+use crate::misc::util::Direction;
+use crate::world::{tile, wall, World};
+use crate::world::support::SupportRule;
+use crate::world::tile::{Tile, TileId};
+use crate::world::wall::Wall;
+
+/// A piece of furniture or decoration that covers more than one tile.
+/// Every part is a regular tile that knows its offset from the bottom left part of the object.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ObjectType {
+	pub tile: TileId,
+	pub width: u8,
+	pub height: u8,
+	pub anchor: SupportRule,
+	/// Top left of the object sprite in the object sprite sheet, in tiles.
+	pub sprite_x: u8,
+	pub sprite_y: u8,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PlaceError {
+	NotAnObject,
+	NotLoaded(WorldPos),
+	/// Part of the object would stick out of the world.
+	OutOfWorld,
+	Obstructed(WorldPos),
+	NoAnchor,
+}
+
+/// Size of the object sprite sheet in tiles, which holds every object next to each other.
+pub const OBJECT_SHEET_WIDTH: u8 = 12;
+pub const OBJECT_SHEET_HEIGHT: u8 = 5;
+
+macro_rules! objects {
+    ($($NAME:ident => $WIDTH:literal x $HEIGHT:literal at ($X:literal, $Y:literal), $ANCHOR:ident;)*) => {
+		impl ObjectType {
+			/// Gets the object which uses `id` as its tile, if `id` is a multi tile object.
+			pub fn get(id: &TileId) -> Option<ObjectType> {
+				match *id {
+					$(tile::$NAME => Some(ObjectType {
+						tile: tile::$NAME,
+						width: $WIDTH,
+						height: $HEIGHT,
+						anchor: SupportRule::$ANCHOR,
+						sprite_x: $X,
+						sprite_y: $Y,
+					}),)*
+					_ => None,
+				}
+			}
+
+			/// Every object type.
+			pub fn get_all() -> Vec<ObjectType> {
+				[$(tile::$NAME),*].iter().filter_map(ObjectType::get).collect()
+			}
+		}
+	};
+}
+
+// The first row of the sheet holds objects up to 3 tiles high, the rows below the ones up to 2 tiles high.
+objects! {
+    DOOR => 1 x 3 at (0, 0), SolidBelow;
+    TABLE => 3 x 2 at (1, 0), SolidBelow;
+    CHAIR => 1 x 2 at (4, 0), SolidBelow;
+    ANVIL => 2 x 1 at (5, 0), SolidBelow;
+    FURNACE => 3 x 2 at (7, 0), SolidBelow;
+    WORK_BENCH => 2 x 1 at (10, 0), SolidBelow;
+    CHEST => 2 x 2 at (0, 3), SolidBelow;
+    DEMON_ALTAR => 3 x 2 at (2, 3), SolidBelow;
+    SIGN => 2 x 2 at (5, 3), AnySide;
+}
+
+impl ObjectType {
+	/// All of the positions this object covers when its bottom left part is at `origin`. Includes the frame of every part.
+	/// Returns None if the object would stick out of the world.
+	pub fn get_footprint(&self, origin: &WorldPos) -> Option<Vec<(WorldPos, u8, u8)>> {
+		let mut out = Vec::with_capacity(self.width as usize * self.height as usize);
+		for frame_y in 0..self.height {
+			for frame_x in 0..self.width {
+				let pos = WorldPos::checked_new(origin.get_x() as i64 + frame_x as i64, origin.get_y() as i64 + frame_y as i64)?;
+				out.push((pos, frame_x, frame_y));
+			}
+		}
+		Some(out)
+	}
+
+	/// Position of a part on the sprite sheet. Frames count from the bottom but sprites are drawn from the top.
+	pub fn get_sprite_pos(&self, frame_x: u8, frame_y: u8) -> (u32, u32) {
+		(
+			self.sprite_x as u32 + frame_x as u32,
+			self.sprite_y as u32 + (self.height - 1 - frame_y) as u32,
+		)
+	}
+
+	/// The bottom left part of the object that `tile` at `pos` is a part of.
+	/// Returns None if the frame of the tile points outside of the world, which only a broken tile does.
+	pub fn get_origin(tile: &Tile, pos: &WorldPos) -> Option<WorldPos> {
+		WorldPos::checked_new(pos.get_x() as i64 - tile.frame_x as i64, pos.get_y() as i64 - tile.frame_y as i64)
+	}
+
+	/// Checks that every part of the object is still in place.
+	pub fn is_complete(&self, world: &World, origin: &WorldPos) -> bool {
+		let footprint = match self.get_footprint(origin) {
+			Some(footprint) => footprint,
+			None => return false,
+		};
+		footprint.iter().all(|(pos, frame_x, frame_y)| {
+			// Unloaded parts are assumed to be fine.
+			match world.get::<Tile>(pos) {
+				Some(tile) => tile.id == self.tile && tile.frame_x == *frame_x && tile.frame_y == *frame_y,
+				None => true,
+			}
+		})
+	}
+
+	/// Checks if the object has what its anchor requires around its footprint.
+	pub fn is_anchored(&self, world: &World, origin: &WorldPos) -> bool {
+		let footprint = match self.get_footprint(origin) {
+			Some(footprint) => footprint,
+			None => return false,
+		};
+		let solid = |pos: &WorldPos, direction: Direction| match pos.shift(direction) {
+			Some(neighbor) => !matches!(world.get::<Tile>(&neighbor), Some(tile) if tile.id == tile::AIR),
+			None => true,
+		};
+		let below = || footprint.iter().filter(|(_, _, frame_y)| *frame_y == 0).all(|(pos, _, _)| solid(pos, Direction::Down));
+		let above = || footprint.iter().filter(|(_, _, frame_y)| *frame_y == self.height - 1).all(|(pos, _, _)| solid(pos, Direction::Top));
+		let wall = || footprint.iter().all(|(pos, _, _)| !matches!(world.get::<Wall>(pos), Some(wall) if wall.id == wall::AIR));
+
+		match self.anchor {
+			SupportRule::None => true,
+			SupportRule::SolidBelow => below(),
+			SupportRule::SolidAbove => above(),
+			SupportRule::WallBehind => wall(),
+			SupportRule::AnySide => below() || above() || wall(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+
+	use super::*;
+	use crate::item::ItemId;
+	use crate::item::stack::ItemStack;
+	use crate::misc::pos::ChunkPos;
+	use crate::world::testing::{create_chunk, create_world};
+
+	/// Two chunks side by side and the two above them, with a stone floor at y 0 and a stone ledge at y 21 for x 40 and up.
+	fn create_seam_world() -> World {
+		let floor = |_, y| if y == 0 { tile::STONE } else { tile::AIR };
+		let ledge = |x, y| if y == 0 || (y == 21 && x >= 16) { tile::STONE } else { tile::AIR };
+		create_world(vec![
+			(ChunkPos::new(0, 0), create_chunk(floor)),
+			(ChunkPos::new(1, 0), create_chunk(ledge)),
+			(ChunkPos::new(0, 1), create_chunk(|_, _| tile::AIR)),
+			(ChunkPos::new(1, 1), create_chunk(|_, _| tile::AIR)),
+		])
+	}
+
+	fn get_part(world: &World, x: i32, y: u32) -> (TileId, u8, u8) {
+		let tile = world.get::<Tile>(&WorldPos::new(x, y)).unwrap();
+		(tile.id, tile.frame_x, tile.frame_y)
+	}
+
+	#[test]
+	fn sprites_fit_the_sheet_without_overlapping() {
+		let mut used = HashSet::new();
+		for object in ObjectType::get_all() {
+			for frame_y in 0..object.height {
+				for frame_x in 0..object.width {
+					let (x, y) = object.get_sprite_pos(frame_x, frame_y);
+					assert!(x < OBJECT_SHEET_WIDTH as u32 && y < OBJECT_SHEET_HEIGHT as u32, "{:?} is outside of the sheet", object.tile);
+					assert!(used.insert((x, y)), "{:?} overlaps another object", object.tile);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn origin_outside_of_the_world_is_rejected() {
+		let tile = Tile::part(tile::CHEST, 1, 1);
+		assert_eq!(ObjectType::get_origin(&tile, &WorldPos::new(5, 5)), Some(WorldPos::new(4, 4)));
+		assert_eq!(ObjectType::get_origin(&tile, &WorldPos::new(WorldPos::MIN_X, 5)), None);
+		assert_eq!(ObjectType::get_origin(&tile, &WorldPos::new(5, 0)), None);
+	}
+
+	#[test]
+	fn footprint_outside_of_the_world_is_rejected() {
+		let chest = ObjectType::get(&tile::CHEST).unwrap();
+		assert_eq!(chest.get_footprint(&WorldPos::new(0, 0)).map(|footprint| footprint.len()), Some(4));
+		assert!(chest.get_footprint(&WorldPos::new(WorldPos::MAX_X, 0)).is_none());
+		assert!(chest.get_footprint(&WorldPos::new(0, WorldPos::MAX_Y)).is_none());
+	}
+
+	#[test]
+	fn objects_get_placed_across_chunk_seams() {
+		let mut world = create_seam_world();
+		// The chunk seam is between x 23 and 24, so the table sits in both chunks.
+		world.place_object(&WorldPos::new(22, 1), tile::TABLE).unwrap();
+		for frame_y in 0..2 {
+			for frame_x in 0..3 {
+				assert_eq!(get_part(&world, 22 + frame_x as i32, 1 + frame_y as u32), (tile::TABLE, frame_x, frame_y));
+			}
+		}
+
+		// The door stands on the ledge and reaches into the chunk above.
+		world.place_object(&WorldPos::new(41, 22), tile::DOOR).unwrap();
+		for frame_y in 0..3 {
+			assert_eq!(get_part(&world, 41, 22 + frame_y as u32), (tile::DOOR, 0, frame_y));
+		}
+	}
+
+	#[test]
+	fn objects_across_chunk_seams_break_whole_and_drop_once() {
+		let mut world = create_seam_world();
+		world.place_object(&WorldPos::new(22, 1), tile::TABLE).unwrap();
+		world.place_object(&WorldPos::new(41, 22), tile::DOOR).unwrap();
+
+		// Breaking a part in either chunk breaks the whole object.
+		assert!(world.break_tile(&WorldPos::new(24, 2)));
+		assert!(world.break_tile(&WorldPos::new(41, 24)));
+		for x in 22..25 {
+			for y in 1..3 {
+				assert_eq!(get_part(&world, x, y).0, tile::AIR);
+			}
+		}
+		for y in 22..25 {
+			assert_eq!(get_part(&world, 41, y).0, tile::AIR);
+		}
+
+		let drops: Vec<ItemStack> = world.get_drops().iter().map(|drop| drop.stack).collect();
+		assert_eq!(drops, vec![ItemStack::new(ItemId::Block(tile::TABLE), 1), ItemStack::new(ItemId::Block(tile::DOOR), 1)]);
+	}
+}
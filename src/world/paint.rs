@@ -0,0 +1,178 @@
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+use crate::world::tile;
+use crate::world::save::invalid_data;
+use crate::world::tile::Tile;
+
+/// Something that can be painted with a bucket of paint.
+/// Walls keep the paint they were saved with, but are not drawn yet, so only tiles can be painted for now.
+pub trait Paintable {
+	fn get_paint(&self) -> PaintId;
+
+	fn set_paint(&mut self, paint: PaintId);
+
+	/// Air for example cannot hold any paint.
+	fn can_paint(&self) -> bool;
+}
+
+impl Paintable for Tile {
+	fn get_paint(&self) -> PaintId {
+		self.paint
+	}
+
+	fn set_paint(&mut self, paint: PaintId) {
+		self.paint = paint;
+	}
+
+	fn can_paint(&self) -> bool {
+		self.id != tile::AIR
+	}
+}
+
+/// No paint, which does not tint.
+pub const NONE: PaintId = PaintId { id: 0 };
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Ord, PartialOrd)]
+pub struct PaintId {
+	pub id: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Paint {
+	pub id: PaintId,
+	pub key: String,
+	/// The color this paint multiplies the sprite with.
+	pub color: (f32, f32, f32),
+}
+
+/// Every paint, loaded from a text file with one paint per line: `<key> <id> <red> <green> <blue>`.
+/// Color channels go from 0 to 255 and `#` starts a comment.
+#[derive(Clone, Debug, Default)]
+pub struct PaintPalette {
+	paints: Vec<Paint>,
+}
+
+impl PaintPalette {
+	pub fn load(path: &Path) -> Result<PaintPalette> {
+		Self::parse(&fs::read_to_string(path)?)
+	}
+
+	pub fn parse(text: &str) -> Result<PaintPalette> {
+		let mut paints: Vec<Paint> = Vec::new();
+		for (index, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or("").trim();
+			if line.is_empty() {
+				continue;
+			}
+
+			let paint = parse_paint(line).map_err(|error| invalid_data(format!("Line {}: {}", index + 1, error)))?;
+			if paints.iter().any(|other| other.id == paint.id || other.key == paint.key) {
+				return Err(invalid_data(format!("Line {}: {} is defined twice", index + 1, paint.key)));
+			}
+			paints.push(paint);
+		}
+		Ok(PaintPalette { paints })
+	}
+
+	pub fn get(&self, id: &PaintId) -> Option<&Paint> {
+		self.paints.iter().find(|paint| paint.id == *id)
+	}
+
+	pub fn get_paints(&self) -> &[Paint] {
+		&self.paints
+	}
+
+	pub fn from_key(&self, key: &str) -> Option<PaintId> {
+		self.paints.iter().find(|paint| paint.key == key).map(|paint| paint.id)
+	}
+
+	/// The color of the paint. Unknown paints do not tint.
+	pub fn get_color(&self, id: &PaintId) -> (f32, f32, f32) {
+		self.get(id).map_or((1f32, 1f32, 1f32), |paint| paint.color)
+	}
+}
+
+fn parse_paint(line: &str) -> Result<Paint> {
+	let parts: Vec<&str> = line.split_whitespace().collect();
+	if parts.len() != 5 {
+		return Err(invalid_data(format!("Expected <key> <id> <red> <green> <blue> but got {} values", parts.len())));
+	}
+
+	let id = parts[1].parse::<u8>().map_err(|_| invalid_data(format!("Invalid id {}", parts[1])))?;
+	let mut channels = [0f32; 3];
+	for (channel, text) in channels.iter_mut().zip(&parts[2..]) {
+		*channel = text.parse::<u8>().map_err(|_| invalid_data(format!("Invalid color {}", text)))? as f32 / 255f32;
+	}
+	Ok(Paint {
+		id: PaintId { id },
+		key: parts[0].to_string(),
+		color: (channels[0], channels[1], channels[2]),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::World;
+
+	#[test]
+	fn parses_paints() {
+		let palette = PaintPalette::parse("# Paints\nRED 1 255 0 0\n\nGRAY 27 175 175 175 # Comment\n").unwrap();
+		assert_eq!(palette.get_paints().len(), 2);
+		assert_eq!(palette.from_key("GRAY"), Some(PaintId { id: 27 }));
+		assert_eq!(palette.get_color(&PaintId { id: 1 }), (1f32, 0f32, 0f32));
+		assert_eq!(palette.get_color(&PaintId { id: 27 }).0, 175f32 / 255f32);
+		// Unknown paints do not tint.
+		assert_eq!(palette.get_color(&PaintId { id: 2 }), (1f32, 1f32, 1f32));
+	}
+
+	#[test]
+	fn rejects_invalid_paints() {
+		assert!(PaintPalette::parse("RED 1 255 0").is_err());
+		assert!(PaintPalette::parse("RED 1 256 0 0").is_err());
+		assert!(PaintPalette::parse("RED x 255 0 0").is_err());
+		assert!(PaintPalette::parse("RED 1 255 0 0\nORANGE 1 255 127 0").is_err());
+		assert!(PaintPalette::parse("RED 1 255 0 0\nRED 2 255 127 0").is_err());
+	}
+
+	#[test]
+	fn bundled_palette_loads() {
+		let palette = PaintPalette::load(Path::new("./assets/data/paints.txt")).unwrap();
+		assert_eq!(palette.from_key("SHADOW"), Some(PaintId { id: 29 }));
+		assert!(palette.get(&NONE).is_none());
+	}
+
+	#[test]
+	fn undo_reverts_paint() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, y| if y == 0 { tile::STONE } else { tile::AIR }))]);
+		world.set_paints(PaintPalette::parse("RED 1 255 0 0\nGRAY 27 175 175 175").unwrap());
+		let pos = WorldPos::new(3, 0);
+		let get_paint = |world: &World| world.get::<Tile>(&pos).unwrap().paint;
+
+		world.begin_action();
+		assert!(world.paint::<Tile>(&pos, PaintId { id: 1 }));
+		world.end_action();
+		world.begin_action();
+		assert!(world.paint::<Tile>(&pos, PaintId { id: 27 }));
+		assert!(!world.paint::<Tile>(&pos, PaintId { id: 27 }));
+		world.end_action();
+		assert_eq!(get_paint(&world), PaintId { id: 27 });
+
+		assert!(world.undo());
+		assert_eq!(get_paint(&world), PaintId { id: 1 });
+		assert!(world.undo());
+		assert_eq!(get_paint(&world), NONE);
+		assert!(world.redo());
+		assert_eq!(get_paint(&world), PaintId { id: 1 });
+
+		// Air and paints outside of the palette can not be painted.
+		assert!(!world.paint::<Tile>(&WorldPos::new(3, 1), PaintId { id: 1 }));
+		assert!(!world.paint::<Tile>(&pos, PaintId { id: 2 }));
+		assert!(world.scrape::<Tile>(&pos));
+		assert_eq!(get_paint(&world), NONE);
+	}
+}
@@ -0,0 +1,314 @@
+use crate::misc::pos::WorldPos;
+use crate::misc::util::CollisionType;
+use crate::world::World;
+use crate::world::tile::Tile;
+
+/// How much faster things fall every tick, in tiles per tick.
+pub const GRAVITY: f32 = 0.025;
+/// The fastest anything falls, in tiles per tick.
+pub const TERMINAL_VELOCITY: f32 = 0.625;
+/// The highest ledge a box walks onto without jumping, in tiles.
+pub const STEP_HEIGHT: f32 = 1.0;
+/// Boxes move at most this far at once so fast ones do not skip over tiles.
+const MAX_STEP: f32 = 0.25;
+const EPSILON: f32 = 0.001;
+
+/// An axis aligned box in tile units with its bottom left corner at `x`, `y`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+	pub x: f32,
+	pub y: f32,
+	pub width: f32,
+	pub height: f32,
+}
+
+impl Aabb {
+	pub fn new(x: f32, y: f32, width: f32, height: f32) -> Aabb {
+		Self { x, y, width, height }
+	}
+
+	pub fn offset(&self, x: f32, y: f32) -> Aabb {
+		Aabb { x: self.x + x, y: self.y + y, ..*self }
+	}
+
+	/// If the boxes share some area. Boxes that only touch do not overlap.
+	pub fn overlaps(&self, other: &Aabb) -> bool {
+		self.x < other.x + other.width && other.x < self.x + self.width
+			&& self.y < other.y + other.height && other.y < self.y + self.height
+	}
+}
+
+/// The solid part of a tile that overlaps a box, in world space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Collider {
+	left: f32,
+	right: f32,
+	bottom: f32,
+	top: f32,
+}
+
+/// What a box ran into while moving.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Movement {
+	pub hit_x: bool,
+	pub hit_top: bool,
+	pub on_ground: bool,
+}
+
+impl World {
+	/// Moves the box horizontally and then vertically, stopping at every tile that collides.
+	/// With `step_up` the box walks onto ledges up to `STEP_HEIGHT` high and up platform stairs instead of stopping.
+	/// Platforms only stop boxes that fall onto them from above, unless `drop_through` is set.
+	/// Unloaded chunks and the edge of the world are solid.
+	pub fn move_aabb(&self, aabb: &mut Aabb, x: f32, y: f32, step_up: bool, drop_through: bool) -> Movement {
+		let mut movement = Movement::default();
+		for step in Self::split(x) {
+			let next = aabb.offset(step, 0.0);
+			let colliders = self.get_colliders(&next, None);
+			if colliders.is_empty() {
+				*aabb = self.climb_stairs(aabb, next, step_up && !drop_through);
+				continue;
+			}
+
+			if step_up {
+				let top = colliders.iter().map(|collider| collider.top).fold(f32::MIN, f32::max);
+				let lifted = Aabb { y: top, ..next };
+				if top - aabb.y <= STEP_HEIGHT + EPSILON && !self.collides(&lifted) {
+					*aabb = lifted;
+					continue;
+				}
+			}
+
+			movement.hit_x = true;
+			if step > 0.0 {
+				let left = colliders.iter().map(|collider| collider.left).fold(f32::MAX, f32::min);
+				aabb.x = aabb.x.max(left - aabb.width);
+			} else {
+				let right = colliders.iter().map(|collider| collider.right).fold(f32::MIN, f32::max);
+				aabb.x = aabb.x.min(right);
+			}
+			break;
+		}
+
+		for step in Self::split(y) {
+			let next = aabb.offset(0.0, step);
+			let platforms = (step < 0.0 && !drop_through).then_some(aabb.y);
+			let colliders = self.get_colliders(&next, platforms);
+			if colliders.is_empty() {
+				*aabb = next;
+				continue;
+			}
+
+			if step < 0.0 {
+				movement.on_ground = true;
+				let top = colliders.iter().map(|collider| collider.top).fold(f32::MIN, f32::max);
+				aabb.y = aabb.y.min(top);
+			} else {
+				movement.hit_top = true;
+				let bottom = colliders.iter().map(|collider| collider.bottom).fold(f32::MAX, f32::min);
+				aabb.y = aabb.y.max(bottom - aabb.height);
+			}
+			break;
+		}
+		movement
+	}
+
+	/// If any tile that collides from every side overlaps the box.
+	pub fn collides(&self, aabb: &Aabb) -> bool {
+		!self.get_colliders(aabb, None).is_empty()
+	}
+
+	/// Lifts a box that walked into a sloped platform onto it, so platforms can be used as stairs.
+	fn climb_stairs(&self, aabb: &Aabb, next: Aabb, climb: bool) -> Aabb {
+		if climb {
+			let top = self.get_colliders(&next, Some(aabb.y + MAX_STEP)).iter().map(|collider| collider.top).fold(f32::MIN, f32::max);
+			let lifted = Aabb { y: top, ..next };
+			if top > aabb.y && !self.collides(&lifted) {
+				return lifted;
+			}
+		}
+		next
+	}
+
+	/// The solid parts of the tiles overlapping the box. Platforms only count if their top is at or below `platforms`.
+	fn get_colliders(&self, aabb: &Aabb, platforms: Option<f32>) -> Vec<Collider> {
+		let mut out = Vec::new();
+		let (left, right) = (aabb.x + EPSILON, aabb.x + aabb.width - EPSILON);
+		let (bottom, top) = (aabb.y + EPSILON, aabb.y + aabb.height - EPSILON);
+		for tile_x in left.floor() as i64..=right.floor() as i64 {
+			for tile_y in bottom.floor() as i64..=top.floor() as i64 {
+				let (collision, (solid_bottom, solid_top)) = match WorldPos::checked_new(tile_x, tile_y).map(|pos| self.get::<Tile>(&pos)) {
+					Some(Some(tile)) => (tile.id.get_collision_type(), tile.shape.get_solid_span_over(left - tile_x as f32, right - tile_x as f32)),
+					_ => (CollisionType::CollidesPlayer, (0.0, 1.0)),
+				};
+
+				let collider = Collider {
+					left: tile_x as f32,
+					right: tile_x as f32 + 1.0,
+					bottom: tile_y as f32 + solid_bottom,
+					top: tile_y as f32 + solid_top,
+				};
+				let counts = match collision {
+					CollisionType::CollidesPlayer => true,
+					CollisionType::Platform => matches!(platforms, Some(feet) if collider.top <= feet + EPSILON),
+					CollisionType::Nothing => false,
+				};
+				if counts && collider.top > bottom && collider.bottom < top {
+					out.push(collider);
+				}
+			}
+		}
+		out
+	}
+
+	/// Splits a movement into steps of at most `MAX_STEP`.
+	fn split(distance: f32) -> impl Iterator<Item = f32> {
+		let steps = if distance.is_finite() { (distance.abs() / MAX_STEP).ceil() as u32 } else { 0 };
+		(0..steps).map(move |_| distance / steps as f32)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::world::physics::Aabb;
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile::{Tile, TileShape};
+	use crate::world::{tile, World};
+
+	/// Moves the box in steps like a player walking would, so no single move spans the whole distance.
+	fn walk(world: &World, aabb: &mut Aabb, distance: f32, step_up: bool) -> bool {
+		let mut hit_x = false;
+		for _ in 0..(distance.abs() / 0.1).round() as u32 {
+			hit_x |= world.move_aabb(aabb, 0.1 * distance.signum(), -0.05, step_up, false).hit_x;
+		}
+		hit_x
+	}
+
+	#[test]
+	fn sliding_along_the_floor_does_not_snag_on_tile_corners() {
+		let world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, y| if y == 0 { tile::STONE } else { tile::AIR }))]);
+		let mut aabb = Aabb::new(2.0, 1.0, 0.75, 1.5);
+		assert!(!walk(&world, &mut aabb, 10.0, false));
+		assert!((aabb.x - 12.0).abs() < 0.01);
+		assert_eq!(aabb.y, 1.0);
+
+		// Falling right next to a wall slides past its corners too.
+		let world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| if y == 0 || x == 5 { tile::STONE } else { tile::AIR }))]);
+		let mut aabb = Aabb::new(4.25, 10.0, 0.75, 1.5);
+		let movement = world.move_aabb(&mut aabb, 0.0, -5.0, false, false);
+		assert!(!movement.hit_x && !movement.on_ground);
+		assert!((aabb.y - 5.0).abs() < 0.01);
+	}
+
+	#[test]
+	fn steps_up_single_ledges_only() {
+		let world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| match (x, y) {
+			(_, 0) | (6.., 1) | (12.., 2..=3) => tile::STONE,
+			_ => tile::AIR,
+		}))]);
+
+		let mut aabb = Aabb::new(2.0, 1.0, 0.75, 1.5);
+		assert!(!walk(&world, &mut aabb, 6.0, true));
+		assert_eq!(aabb.y, 2.0);
+
+		// Two tiles are too high to step onto.
+		assert!(walk(&world, &mut aabb, 6.0, true));
+		assert!((aabb.x - (12.0 - 0.75)).abs() < 0.01);
+		assert_eq!(aabb.y, 2.0);
+
+		// Without stepping up even a single tile stops the box.
+		let mut aabb = Aabb::new(2.0, 1.0, 0.75, 1.5);
+		assert!(walk(&world, &mut aabb, 6.0, false));
+		assert!((aabb.x - (6.0 - 0.75)).abs() < 0.01);
+	}
+
+	#[test]
+	fn moves_across_chunk_seams() {
+		let floor = |_, y| if y == 0 { tile::STONE } else { tile::AIR };
+		let world = create_world(vec![(ChunkPos::new(-1, 0), create_chunk(floor)), (ChunkPos::new(0, 0), create_chunk(floor))]);
+		let mut aabb = Aabb::new(-3.0, 1.0, 0.75, 1.5);
+		assert!(!walk(&world, &mut aabb, 6.0, false));
+		assert!((aabb.x - 3.0).abs() < 0.01);
+		assert_eq!(aabb.y, 1.0);
+
+		// Unloaded chunks are solid, so the box stops at the seam.
+		let world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(floor))]);
+		let mut aabb = Aabb::new(3.0, 1.0, 0.75, 1.5);
+		assert!(walk(&world, &mut aabb, -6.0, false));
+		assert!(aabb.x.abs() < 0.01);
+	}
+
+	/// A stone floor at y 0 with a row of platforms at y 3.
+	fn create_platform_world() -> World {
+		create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, y| match y {
+			0 => tile::STONE,
+			3 => tile::PLATFORM,
+			_ => tile::AIR,
+		}))])
+	}
+
+	#[test]
+	fn platforms_catch_boxes_falling_from_above() {
+		let world = create_platform_world();
+		let mut aabb = Aabb::new(2.0, 6.0, 0.75, 1.5);
+		assert!(world.move_aabb(&mut aabb, 0.0, -5.0, false, false).on_ground);
+		assert_eq!(aabb.y, 4.0);
+
+		// Standing on it keeps the box up.
+		assert!(world.move_aabb(&mut aabb, 0.0, -0.5, false, false).on_ground);
+		assert_eq!(aabb.y, 4.0);
+	}
+
+	#[test]
+	fn boxes_pass_up_through_platforms() {
+		let world = create_platform_world();
+		let mut aabb = Aabb::new(2.0, 1.0, 0.75, 1.5);
+		let movement = world.move_aabb(&mut aabb, 0.0, 5.0, false, false);
+		assert!(!movement.hit_top && !movement.on_ground);
+		assert!((aabb.y - 6.0).abs() < 0.01);
+
+		// A box that is still halfway through the platform falls back down instead of getting caught.
+		let mut aabb = Aabb::new(2.0, 3.5, 0.75, 1.5);
+		assert!(world.move_aabb(&mut aabb, 0.0, -5.0, false, false).on_ground);
+		assert_eq!(aabb.y, 1.0);
+	}
+
+	#[test]
+	fn drop_through_falls_through_platforms() {
+		let world = create_platform_world();
+		let mut aabb = Aabb::new(2.0, 4.0, 0.75, 1.5);
+		let movement = world.move_aabb(&mut aabb, 0.0, -0.5, false, true);
+		assert!(!movement.on_ground);
+		assert!((aabb.y - 3.5).abs() < 0.01);
+
+		// The floor below still catches the box.
+		assert!(world.move_aabb(&mut aabb, 0.0, -5.0, false, true).on_ground);
+		assert_eq!(aabb.y, 1.0);
+	}
+
+	#[test]
+	fn walks_up_platform_stairs() {
+		// Sloped platforms going up to the right from x 5 to 8, leading onto a stone ledge from x 9 on with its top at y 5.
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| match (x, y) {
+			(_, 0) | (9.., ..=4) => tile::STONE,
+			_ => tile::AIR,
+		}))]);
+		for step in 0..4 {
+			let mut platform = Tile::id(tile::PLATFORM);
+			platform.shape = TileShape::SlopeBottomRight;
+			world.set(&WorldPos::new(5 + step, 1 + step as u32), platform);
+		}
+
+		let mut aabb = Aabb::new(2.0, 1.0, 0.75, 1.5);
+		assert!(!walk(&world, &mut aabb, 10.0, true));
+		assert!((aabb.x - 12.0).abs() < 0.01);
+		assert!((aabb.y - 5.0).abs() < 0.01);
+
+		// Without stepping up the box walks underneath the stairs into the ledge.
+		let mut aabb = Aabb::new(2.0, 1.0, 0.75, 1.5);
+		assert!(walk(&world, &mut aabb, 10.0, false));
+		assert!((aabb.x - (9.0 - 0.75)).abs() < 0.01);
+		assert_eq!(aabb.y, 1.0);
+	}
+}
@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+
+use crate::misc::pos::{FloatPos, WorldPos};
+use crate::misc::util::{CHUNK_SIZE, CollisionType, Direction};
+use crate::world::{tile, Chunk, Grid, World};
+use crate::world::neighbor::NeighborAware;
+use crate::world::region::Region;
+use crate::world::tile::{Tile, TileId};
+
+/// The first solid tile a ray ran into.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+	pub pos: WorldPos,
+	/// The side of the tile the ray entered through.
+	pub face: Direction,
+	/// Distance from the ray origin in tiles.
+	pub distance: f32,
+}
+
+impl World {
+	/// Walks the tile grid from `origin` along `direction` and returns the first tile that collides.
+	/// Positions are in tiles and `direction` does not need to be normalized. The ray stops at unloaded chunks, the edge of the world and after `max_distance`.
+	/// A ray that starts inside of a solid tile hits it at distance 0.
+	pub fn raycast(&self, origin: &FloatPos, direction: &FloatPos, max_distance: f32) -> Option<RayHit> {
+		let length = (direction.x * direction.x + direction.y * direction.y).sqrt();
+		if length == 0.0 || !length.is_finite() || !origin.x.is_finite() || !origin.y.is_finite() {
+			return None;
+		}
+
+		let (dir_x, dir_y) = (direction.x / length, direction.y / length);
+		let mut x = origin.x.floor() as i64;
+		let mut y = origin.y.floor() as i64;
+		let (step_x, delta_x, mut next_x) = get_ray_axis(origin.x, x, dir_x);
+		let (step_y, delta_y, mut next_y) = get_ray_axis(origin.y, y, dir_y);
+
+		let mut distance = 0.0;
+		let mut face = if dir_x.abs() > dir_y.abs() {
+			if step_x > 0 { Direction::Left } else { Direction::Right }
+		} else if step_y > 0 {
+			Direction::Down
+		} else {
+			Direction::Top
+		};
+
+		loop {
+			let pos = WorldPos::checked_new(x, y)?;
+			match self.get::<Tile>(&pos) {
+				None => return None,
+				Some(tile) if tile.id.get_collision_type() == CollisionType::CollidesPlayer => {
+					return Some(RayHit { pos, face, distance });
+				}
+				Some(_) => {}
+			}
+
+			if next_x < next_y {
+				distance = next_x;
+				x += step_x;
+				next_x += delta_x;
+				face = if step_x > 0 { Direction::Left } else { Direction::Right };
+			} else {
+				distance = next_y;
+				y += step_y;
+				next_y += delta_y;
+				face = if step_y > 0 { Direction::Down } else { Direction::Top };
+			}
+
+			if distance > max_distance {
+				return None;
+			}
+		}
+	}
+
+	/// Every loaded object in the region. Positions come grouped by chunk so every chunk only gets looked up once.
+	pub fn iter_rect<'a, C: NeighborAware + 'a>(&'a self, region: &Region) -> impl Iterator<Item = (WorldPos, &'a C)> + 'a where Chunk: Grid<C> {
+		let region = *region;
+		region.get_chunks().into_iter()
+			.filter_map(move |chunk_pos| self.chunks.get(&chunk_pos).map(|chunk| (chunk_pos, chunk)))
+			.flat_map(move |(chunk_pos, chunk)| {
+				let last = (CHUNK_SIZE - 1) as u8;
+				let chunk_region = Region::new(&WorldPos::from_chunk(&chunk_pos, 0, 0), &WorldPos::from_chunk(&chunk_pos, last, last));
+				region.intersect(&chunk_region).into_iter()
+					.flat_map(|overlap| overlap.iter())
+					.map(move |pos| (pos, chunk.get(pos.get_chunk_sub_pos())))
+			})
+	}
+
+	/// Every loaded object whose center is at most `radius` tiles away from the center of `center`.
+	pub fn iter_circle<'a, C: NeighborAware + 'a>(&'a self, center: &WorldPos, radius: u32) -> impl Iterator<Item = (WorldPos, &'a C)> + 'a where Chunk: Grid<C> {
+		let (center_x, center_y) = (center.get_x() as i64, center.get_y() as i64);
+		let radius = radius as i64;
+		let min = WorldPos::new((center_x - radius).max(WorldPos::MIN_X as i64) as i32, (center_y - radius).max(WorldPos::MIN_Y as i64) as u32);
+		let max = WorldPos::new((center_x + radius).min(WorldPos::MAX_X as i64) as i32, (center_y + radius).min(WorldPos::MAX_Y as i64) as u32);
+		self.iter_rect(&Region::new(&min, &max)).filter(move |(pos, _)| {
+			let x = pos.get_x() as i64 - center_x;
+			let y = pos.get_y() as i64 - center_y;
+			x * x + y * y <= radius * radius
+		})
+	}
+
+	/// Every kind of loaded tile at most `range_x` and `range_y` tiles away from `center`, like the crafting stations around a player.
+	pub fn get_tiles_near(&self, center: &WorldPos, range_x: u32, range_y: u32) -> HashSet<TileId> {
+		let (center_x, center_y) = (center.get_x() as i64, center.get_y() as i64);
+		let min = WorldPos::new((center_x - range_x as i64).max(WorldPos::MIN_X as i64) as i32, (center_y - range_y as i64).max(WorldPos::MIN_Y as i64) as u32);
+		let max = WorldPos::new((center_x + range_x as i64).min(WorldPos::MAX_X as i64) as i32, (center_y + range_y as i64).min(WorldPos::MAX_Y as i64) as u32);
+		self.iter_rect::<Tile>(&Region::new(&min, &max))
+			.map(|(_, tile)| tile.id)
+			.filter(|id| *id != tile::AIR)
+			.collect()
+	}
+}
+
+/// The step, the distance along the ray between two grid lines and the distance to the first grid line on one axis.
+/// An axis the ray does not move along never reaches a grid line, which also keeps `0 * inf` out of the distances.
+fn get_ray_axis(origin: f32, cell: i64, dir: f32) -> (i64, f32, f32) {
+	let delta = (1.0 / dir).abs();
+	if dir == 0.0 || !delta.is_finite() {
+		(0, f32::INFINITY, f32::INFINITY)
+	} else if dir > 0.0 {
+		(1, delta, (cell as f32 + 1.0 - origin) * delta)
+	} else {
+		(-1, delta, (origin - cell as f32) * delta)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+
+	use super::*;
+	use crate::misc::pos::ChunkPos;
+	use crate::world::testing::{create_chunk, create_world};
+
+	/// One chunk with a stone floor at y 0 and a stone wall at x 4.
+	fn create_walled_world() -> World {
+		create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| if x == 4 || y == 0 { tile::STONE } else { tile::AIR }))])
+	}
+
+	fn assert_hit(hit: Option<RayHit>, pos: WorldPos, face: Direction, distance: f32) {
+		let hit = hit.expect("The ray did not hit anything");
+		assert_eq!((hit.pos, hit.face), (pos, face));
+		assert!((hit.distance - distance).abs() < 0.0001, "Hit at {} instead of {}", hit.distance, distance);
+	}
+
+	#[test]
+	fn diagonal_rays_hit_the_face_they_enter_through() {
+		let world = create_walled_world();
+		// Crosses x 4 at y 4.25, 3.5 tiles right and 1.75 tiles up.
+		assert_hit(world.raycast(&FloatPos::new(0.5, 2.5), &FloatPos::new(2.0, 1.0), 10.0), WorldPos::new(4, 4), Direction::Left, (3.5f32 * 3.5 + 1.75 * 1.75).sqrt());
+		// Crosses y 1 at x 1.75, 1.25 tiles right and 2.5 tiles down.
+		assert_hit(world.raycast(&FloatPos::new(0.5, 3.5), &FloatPos::new(1.0, -2.0), 10.0), WorldPos::new(1, 0), Direction::Top, (1.25f32 * 1.25 + 2.5 * 2.5).sqrt());
+		assert_hit(world.raycast(&FloatPos::new(4.5, 7.5), &FloatPos::new(1.0, 1.0), 10.0), WorldPos::new(4, 7), Direction::Down, 0.0);
+		assert_eq!(world.raycast(&FloatPos::new(0.5, 2.5), &FloatPos::new(2.0, 1.0), 3.5), None);
+	}
+
+	#[test]
+	fn axis_aligned_rays_only_step_along_their_axis() {
+		let world = create_walled_world();
+		assert_hit(world.raycast(&FloatPos::new(0.5, 5.5), &FloatPos::new(1.0, 0.0), 10.0), WorldPos::new(4, 5), Direction::Left, 3.5);
+		assert_hit(world.raycast(&FloatPos::new(10.5, 5.5), &FloatPos::new(-3.0, 0.0), 10.0), WorldPos::new(4, 5), Direction::Right, 5.5);
+		assert_hit(world.raycast(&FloatPos::new(0.5, 5.5), &FloatPos::new(0.0, -1.0), 10.0), WorldPos::new(0, 0), Direction::Top, 4.5);
+		assert_hit(world.raycast(&FloatPos::new(0.5, 5.5), &FloatPos::new(-0.0, -1.0), 10.0), WorldPos::new(0, 0), Direction::Top, 4.5);
+		// Starting on a grid line right next to the wall.
+		assert_hit(world.raycast(&FloatPos::new(5.0, 5.5), &FloatPos::new(-1.0, 0.0), 10.0), WorldPos::new(4, 5), Direction::Right, 0.0);
+		assert_hit(world.raycast(&FloatPos::new(0.0, 5.5), &FloatPos::new(1.0, 0.0), 10.0), WorldPos::new(4, 5), Direction::Left, 4.0);
+		// Straight up runs into the unloaded chunk above.
+		assert_eq!(world.raycast(&FloatPos::new(0.5, 5.5), &FloatPos::new(0.0, 1.0), 100.0), None);
+		assert_eq!(world.raycast(&FloatPos::new(0.5, 5.5), &FloatPos::new(0.0, 0.0), 100.0), None);
+	}
+
+	#[test]
+	fn rays_and_rects_cross_chunk_seams_and_stop_at_unloaded_chunks() {
+		// A stone column at x 30, the chunk right of it is not loaded.
+		let world = create_world(vec![
+			(ChunkPos::new(0, 0), create_chunk(|_, _| tile::AIR)),
+			(ChunkPos::new(1, 0), create_chunk(|x, _| if x == 6 { tile::STONE } else { tile::AIR })),
+		]);
+		assert_hit(world.raycast(&FloatPos::new(20.5, 5.5), &FloatPos::new(1.0, 0.0), 20.0), WorldPos::new(30, 5), Direction::Left, 9.5);
+		assert_eq!(world.raycast(&FloatPos::new(20.5, 5.5), &FloatPos::new(1.0, 0.0), 9.0), None);
+		assert_eq!(world.raycast(&FloatPos::new(34.5, 5.5), &FloatPos::new(1.0, 0.0), 100.0), None);
+		assert_eq!(world.raycast(&FloatPos::new(20.5, 5.5), &FloatPos::new(-1.0, 0.0), 100.0), None);
+
+		let positions: Vec<WorldPos> = world.iter_rect::<Tile>(&Region::new(&WorldPos::new(20, 2), &WorldPos::new(50, 3))).map(|(pos, _)| pos).collect();
+		let expected: HashSet<WorldPos> = (20..48).flat_map(|x| [WorldPos::new(x, 2), WorldPos::new(x, 3)]).collect();
+		assert_eq!(positions.len(), expected.len());
+		assert_eq!(positions.into_iter().collect::<HashSet<_>>(), expected);
+
+		let stone: Vec<WorldPos> = world.iter_rect::<Tile>(&Region::new(&WorldPos::new(-5, 2), &WorldPos::new(50, 3)))
+			.filter(|(_, tile)| tile.id == tile::STONE)
+			.map(|(pos, _)| pos)
+			.collect();
+		assert_eq!(stone, vec![WorldPos::new(30, 2), WorldPos::new(30, 3)]);
+	}
+
+	#[test]
+	fn iter_circle_includes_its_boundary_and_nothing_outside() {
+		let world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, _| tile::AIR))]);
+		let circle = |center_x: i32, center_y: u32| -> HashSet<WorldPos> {
+			world.iter_circle::<Tile>(&WorldPos::new(center_x, center_y), 3).map(|(pos, _)| pos).collect()
+		};
+
+		let positions = circle(10, 10);
+		let expected: HashSet<WorldPos> = (-5..=5).flat_map(|x| (-5..=5).map(move |y| (x, y)))
+			.filter(|(x, y)| x * x + y * y <= 9)
+			.map(|(x, y)| WorldPos::new(10 + x, (10 + y) as u32))
+			.collect();
+		assert_eq!(positions.len(), 29);
+		assert_eq!(positions, expected);
+		assert!(positions.contains(&WorldPos::new(13, 10)) && positions.contains(&WorldPos::new(10, 7)));
+		assert!(!positions.contains(&WorldPos::new(12, 13)) && !positions.contains(&WorldPos::new(13, 11)));
+
+		// The part in the unloaded chunk left of x 0 is left out.
+		let positions = circle(1, 10);
+		assert_eq!(positions, expected.iter().map(|pos| WorldPos::new(pos.get_x() - 9, pos.get_y())).filter(|pos| pos.get_x() >= 0).collect());
+	}
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use crate::misc::pos::{ChunkPos, WorldPos};
+use crate::world::liquid::Liquid;
+use crate::world::object::ObjectType;
+use crate::world::tile::Tile;
+use crate::world::tile_entity::TileEntity;
+use crate::world::wall::Wall;
+
+/// An inclusive rectangle of world positions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Region {
+	min_x: i32,
+	min_y: u32,
+	max_x: i32,
+	max_y: u32,
+}
+
+impl Region {
+	/// The rectangle between two opposite corners, in any order.
+	pub fn new(a: &WorldPos, b: &WorldPos) -> Region {
+		Self {
+			min_x: a.get_x().min(b.get_x()),
+			min_y: a.get_y().min(b.get_y()),
+			max_x: a.get_x().max(b.get_x()),
+			max_y: a.get_y().max(b.get_y()),
+		}
+	}
+
+	/// The rectangle with its bottom left corner at `origin`. Returns None if it is empty or sticks out of the world.
+	pub fn from_size(origin: &WorldPos, width: u32, height: u32) -> Option<Region> {
+		if width == 0 || height == 0 {
+			return None;
+		}
+		let max = WorldPos::checked_new(origin.get_x() as i64 + width as i64 - 1, origin.get_y() as i64 + height as i64 - 1)?;
+		Some(Region::new(origin, &max))
+	}
+
+	pub fn get_min(&self) -> WorldPos {
+		WorldPos::new(self.min_x, self.min_y)
+	}
+
+	pub fn get_max(&self) -> WorldPos {
+		WorldPos::new(self.max_x, self.max_y)
+	}
+
+	pub fn get_width(&self) -> u32 {
+		(self.max_x as i64 - self.min_x as i64) as u32 + 1
+	}
+
+	pub fn get_height(&self) -> u32 {
+		self.max_y - self.min_y + 1
+	}
+
+	pub fn contains(&self, pos: &WorldPos) -> bool {
+		(self.min_x..=self.max_x).contains(&pos.get_x()) && (self.min_y..=self.max_y).contains(&pos.get_y())
+	}
+
+	/// The part both regions cover, if they overlap.
+	pub fn intersect(&self, other: &Region) -> Option<Region> {
+		let region = Region {
+			min_x: self.min_x.max(other.min_x),
+			min_y: self.min_y.max(other.min_y),
+			max_x: self.max_x.min(other.max_x),
+			max_y: self.max_y.min(other.max_y),
+		};
+		(region.min_x <= region.max_x && region.min_y <= region.max_y).then_some(region)
+	}
+
+	/// Every position in the region, row by row starting at the bottom left.
+	pub fn iter(&self) -> impl Iterator<Item = WorldPos> {
+		let (min_x, max_x) = (self.min_x, self.max_x);
+		(self.min_y..=self.max_y).flat_map(move |y| (min_x..=max_x).map(move |x| WorldPos::new(x, y)))
+	}
+
+	/// Every chunk the region touches.
+	pub fn get_chunks(&self) -> Vec<ChunkPos> {
+		let min = self.get_min();
+		let max = self.get_max();
+		let mut out = Vec::new();
+		for y in min.get_chunk_pos().y..=max.get_chunk_pos().y {
+			for x in min.get_chunk_pos().x..=max.get_chunk_pos().x {
+				out.push(ChunkPos::new(x, y));
+			}
+		}
+		out
+	}
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Rotation {
+	None,
+	Clockwise,
+	Half,
+	CounterClockwise,
+}
+
+/// How a clipboard gets turned before pasting. Mirroring happens before the rotation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Transform {
+	pub mirror: bool,
+	pub rotation: Rotation,
+}
+
+impl Default for Transform {
+	fn default() -> Self {
+		Self {
+			mirror: false,
+			rotation: Rotation::None,
+		}
+	}
+}
+
+/// Everything a clipboard holds about a single position.
+#[derive(Copy, Clone)]
+pub struct ClipboardCell {
+	pub tile: Tile,
+	pub wall: Wall,
+	pub liquid: Liquid,
+}
+
+/// A copied rectangle of the world. Positions are relative to its bottom left corner.
+/// Cells that were not loaded while copying are empty and get skipped when pasting.
+#[derive(Clone)]
+pub struct Clipboard {
+	width: u32,
+	height: u32,
+	cells: Vec<Option<ClipboardCell>>,
+	tile_entities: HashMap<(u32, u32), TileEntity>,
+}
+
+impl Clipboard {
+	pub fn new(width: u32, height: u32) -> Clipboard {
+		Self {
+			width,
+			height,
+			cells: vec![None; width as usize * height as usize],
+			tile_entities: HashMap::new(),
+		}
+	}
+
+	pub fn get_width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn get_height(&self) -> u32 {
+		self.height
+	}
+
+	pub fn get(&self, x: u32, y: u32) -> Option<&ClipboardCell> {
+		self.cells[self.get_index(x, y)].as_ref()
+	}
+
+	pub fn set(&mut self, x: u32, y: u32, cell: Option<ClipboardCell>) {
+		let index = self.get_index(x, y);
+		self.cells[index] = cell;
+	}
+
+	pub fn get_tile_entity(&self, x: u32, y: u32) -> Option<&TileEntity> {
+		self.tile_entities.get(&(x, y))
+	}
+
+	pub fn set_tile_entity(&mut self, x: u32, y: u32, entity: TileEntity) {
+		self.tile_entities.insert((x, y), entity);
+	}
+
+	pub fn get_tile_entities(&self) -> &HashMap<(u32, u32), TileEntity> {
+		&self.tile_entities
+	}
+
+	fn get_index(&self, x: u32, y: u32) -> usize {
+		assert!(x < self.width && y < self.height, "{} {} is outside of the clipboard", x, y);
+		x as usize + y as usize * self.width as usize
+	}
+
+	pub fn transform(&self, transform: &Transform) -> Clipboard {
+		let clipboard = if transform.mirror { self.mirror_x() } else { self.clone() };
+		match transform.rotation {
+			Rotation::None => clipboard,
+			Rotation::Clockwise => clipboard.rotate_clockwise(),
+			Rotation::Half => clipboard.mirror_x().mirror_y(),
+			Rotation::CounterClockwise => clipboard.rotate_clockwise().rotate_clockwise().rotate_clockwise(),
+		}
+	}
+
+	/// Flips the clipboard left to right.
+	pub fn mirror_x(&self) -> Clipboard {
+		let width = self.width;
+		self.remap(width, self.height, |x, y| (width - 1 - x, y), |mut cell| {
+			cell.tile.shape = cell.tile.shape.mirror_x();
+			cell
+		})
+	}
+
+	/// Flips the clipboard top to bottom.
+	pub fn mirror_y(&self) -> Clipboard {
+		let height = self.height;
+		self.remap(self.width, height, |x, y| (x, height - 1 - y), |mut cell| {
+			cell.tile.shape = cell.tile.shape.mirror_y();
+			cell
+		})
+	}
+
+	/// Turns the clipboard a quarter clockwise.
+	pub fn rotate_clockwise(&self) -> Clipboard {
+		let width = self.width;
+		self.remap(self.height, width, |x, y| (y, width - 1 - x), |mut cell| {
+			cell.tile.shape = cell.tile.shape.rotate_clockwise();
+			cell
+		})
+	}
+
+	/// Moves every cell to `position` in a clipboard of the given size.
+	/// Objects can not be turned, so every object that is completely inside of the clipboard gets put back together upright
+	/// where its parts moved to, along with its tile entity. Objects that were cut off while copying, or that would need
+	/// to lie on their side, are left out.
+	fn remap(
+		&self,
+		width: u32,
+		height: u32,
+		position: impl Fn(u32, u32) -> (u32, u32),
+		cell: impl Fn(ClipboardCell) -> ClipboardCell,
+	) -> Clipboard {
+		let mut out = Clipboard::new(width, height);
+		for y in 0..self.height {
+			for x in 0..self.width {
+				let (new_x, new_y) = position(x, y);
+				out.set(new_x, new_y, self.get(x, y).map(|old| {
+					let mut new = cell(*old);
+					if ObjectType::get(&old.tile.id).is_some() {
+						new.tile = Tile::air();
+					}
+					new
+				}));
+			}
+		}
+
+		for (x, y, object) in self.get_objects() {
+			let (object_width, object_height) = (object.width as u32, object.height as u32);
+			let (a_x, a_y) = position(x, y);
+			let (b_x, b_y) = position(x + object_width - 1, y + object_height - 1);
+			if a_x.abs_diff(b_x) + 1 != object_width || a_y.abs_diff(b_y) + 1 != object_height {
+				continue;
+			}
+
+			let (origin_x, origin_y) = (a_x.min(b_x), a_y.min(b_y));
+			for frame_y in 0..object.height {
+				for frame_x in 0..object.width {
+					let part = self.get(x + frame_x as u32, y + frame_y as u32).unwrap().tile;
+					let index = out.get_index(origin_x + frame_x as u32, origin_y + frame_y as u32);
+					if let Some(cell) = &mut out.cells[index] {
+						cell.tile = part;
+					}
+				}
+			}
+			if let Some(entity) = self.get_tile_entity(x, y) {
+				out.set_tile_entity(origin_x, origin_y, entity.clone());
+			}
+		}
+		out
+	}
+
+	/// The bottom left corner of every object that has all of its parts inside of the clipboard.
+	fn get_objects(&self) -> Vec<(u32, u32, ObjectType)> {
+		let mut out = Vec::new();
+		for y in 0..self.height {
+			for x in 0..self.width {
+				let origin = match self.get(x, y) {
+					Some(cell) if cell.tile.frame_x == 0 && cell.tile.frame_y == 0 => cell.tile,
+					_ => continue,
+				};
+				let object = match ObjectType::get(&origin.id) {
+					Some(object) => object,
+					None => continue,
+				};
+
+				let complete = (0..object.height).all(|frame_y| (0..object.width).all(|frame_x| {
+					let (part_x, part_y) = (x + frame_x as u32, y + frame_y as u32);
+					part_x < self.width && part_y < self.height && matches!(
+						self.get(part_x, part_y),
+						Some(part) if part.tile.id == origin.id && part.tile.frame_x == frame_x && part.tile.frame_y == frame_y
+					)
+				}));
+				if complete {
+					out.push((x, y, object));
+				}
+			}
+		}
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::tile::{self, TileId};
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile_entity::{Sign, TileEntity};
+
+	fn create_cell(tile: Tile) -> Option<ClipboardCell> {
+		Some(ClipboardCell { tile, wall: Wall::air(), liquid: Liquid::air() })
+	}
+
+	/// A 4 x 3 clipboard with a sign in the bottom left and a work bench on top of it.
+	fn create_clipboard() -> Clipboard {
+		let mut clipboard = Clipboard::new(4, 3);
+		for y in 0..3 {
+			for x in 0..4 {
+				clipboard.set(x, y, create_cell(Tile::air()));
+			}
+		}
+		for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+			clipboard.set(x, y, create_cell(Tile::part(tile::SIGN, x as u8, y as u8)));
+		}
+		for x in 0..2 {
+			clipboard.set(x, 2, create_cell(Tile::part(tile::WORK_BENCH, x as u8, 0)));
+		}
+		clipboard.set_tile_entity(0, 0, TileEntity::Sign(Sign { text: "Hello".to_string() }));
+		clipboard
+	}
+
+	fn get_tile(clipboard: &Clipboard, x: u32, y: u32) -> (TileId, u8, u8) {
+		let tile = clipboard.get(x, y).unwrap().tile;
+		(tile.id, tile.frame_x, tile.frame_y)
+	}
+
+	#[test]
+	fn from_size_rejects_empty_and_outside_regions() {
+		let origin = WorldPos::new(0, 0);
+		assert!(Region::from_size(&origin, 0, 5).is_none());
+		assert!(Region::from_size(&origin, 5, 0).is_none());
+		assert!(Region::from_size(&WorldPos::new(WorldPos::MAX_X, 0), 2, 1).is_none());
+		assert_eq!(Region::from_size(&origin, 3, 2), Some(Region::new(&origin, &WorldPos::new(2, 1))));
+	}
+
+	#[test]
+	fn mirroring_keeps_objects_intact() {
+		let mirrored = create_clipboard().mirror_x();
+		assert_eq!(get_tile(&mirrored, 2, 0), (tile::SIGN, 0, 0));
+		assert_eq!(get_tile(&mirrored, 3, 1), (tile::SIGN, 1, 1));
+		assert_eq!(get_tile(&mirrored, 2, 2), (tile::WORK_BENCH, 0, 0));
+		assert_eq!(get_tile(&mirrored, 3, 2), (tile::WORK_BENCH, 1, 0));
+		assert_eq!(get_tile(&mirrored, 1, 2), (tile::AIR, 0, 0));
+		assert!(mirrored.get_tile_entity(2, 0).is_some());
+	}
+
+	#[test]
+	fn rotating_keeps_objects_that_fit_upright() {
+		let rotated = create_clipboard().rotate_clockwise();
+		assert_eq!((rotated.get_width(), rotated.get_height()), (3, 4));
+		// The sign is square, so it can stand upright in its new place. The work bench would have to lie on its side.
+		assert_eq!(get_tile(&rotated, 0, 2), (tile::SIGN, 0, 0));
+		assert_eq!(get_tile(&rotated, 1, 3), (tile::SIGN, 1, 1));
+		assert!(rotated.get_tile_entity(0, 2).is_some());
+		for y in 0..4 {
+			assert_ne!(get_tile(&rotated, 2, y).0, tile::WORK_BENCH);
+		}
+	}
+
+	#[test]
+	fn cut_off_objects_are_left_out() {
+		let mut clipboard = create_clipboard();
+		clipboard.set(1, 2, create_cell(Tile::air()));
+		let mirrored = clipboard.transform(&Transform { mirror: true, rotation: Rotation::Half });
+		for y in 0..3 {
+			for x in 0..4 {
+				assert_ne!(get_tile(&mirrored, x, y).0, tile::WORK_BENCH);
+			}
+		}
+		// Turning by half keeps the sign upright at the opposite corner.
+		assert_eq!(get_tile(&mirrored, 0, 1), (tile::SIGN, 0, 0));
+	}
+
+	#[test]
+	fn pasting_over_the_world_edge_leaves_the_outside_out() {
+		let chunk_pos = *WorldPos::new(WorldPos::MAX_X, 0).get_chunk_pos();
+		let mut world = create_world(vec![(chunk_pos, create_chunk(|_, _| tile::AIR))]);
+		let mut clipboard = Clipboard::new(2, 1);
+		for x in 0..2 {
+			clipboard.set(x, 0, create_cell(Tile::id(tile::STONE)));
+		}
+
+		let changed = world.paste(&clipboard, &WorldPos::new(WorldPos::MAX_X, 0), &Transform::default());
+		assert_eq!(changed, 3);
+		assert_eq!(world.get::<Tile>(&WorldPos::new(WorldPos::MAX_X, 0)).unwrap().id, tile::STONE);
+	}
+}
@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+use std::fs::{create_dir_all, rename, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+
+use crate::misc::pos::{ChunkPos, ChunkSubPos};
+use crate::misc::util::CHUNK_SIZE;
+use crate::player::Player;
+use crate::world::{Chunk, Grid};
+use crate::world::edit::{PendingEdit, PendingEdits};
+use crate::world::liquid::{Liquid, LiquidKind};
+use crate::world::neighbor::NeighborMatrix;
+use crate::world::paint::PaintId;
+use crate::world::tile::{Tile, TileId, TileShape};
+use crate::world::tile_entity::TileEntity;
+use crate::world::wall::{Wall, WallId};
+
+/// Bump this every time the layout of a chunk file changes.
+const CHUNK_VERSION: u8 = 7;
+/// A chunk can wait for one change per position on each of its three layers.
+const MAX_PENDING_EDITS: usize = CHUNK_SIZE * CHUNK_SIZE * 3;
+
+/// Something that can be written to and read back from a save file.
+pub trait Persistent: Sized {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()>;
+
+	fn read<R: Read>(input: &mut R) -> Result<Self>;
+}
+
+pub fn invalid_data(message: String) -> Error {
+	Error::new(ErrorKind::InvalidData, message)
+}
+
+macro_rules! persistent_number {
+    ($($TYPE:ty),*) => {
+		$(
+		impl Persistent for $TYPE {
+			fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+				out.write_all(&self.to_le_bytes())
+			}
+
+			fn read<R: Read>(input: &mut R) -> Result<Self> {
+				let mut bytes = [0u8; std::mem::size_of::<$TYPE>()];
+				input.read_exact(&mut bytes)?;
+				Ok(<$TYPE>::from_le_bytes(bytes))
+			}
+		}
+		)*
+	};
+}
+
+persistent_number!(u8, u16, u32, u64, i16, i32, f32);
+
+impl Persistent for String {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		(self.len() as u32).write(out)?;
+		out.write_all(self.as_bytes())
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		// The length comes from the file, so the buffer only grows with what actually got read.
+		let len = u32::read(input)? as u64;
+		let mut bytes = Vec::new();
+		input.by_ref().take(len).read_to_end(&mut bytes)?;
+		if (bytes.len() as u64) < len {
+			return Err(Error::new(ErrorKind::UnexpectedEof, "String is cut off"));
+		}
+		String::from_utf8(bytes).map_err(|error| invalid_data(error.to_string()))
+	}
+}
+
+impl Persistent for TileId {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		self.id.write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		Ok(TileId { id: u32::read(input)? })
+	}
+}
+
+impl Persistent for WallId {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		self.id.write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		Ok(WallId { id: u32::read(input)? })
+	}
+}
+
+impl Persistent for PaintId {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		self.id.write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		Ok(PaintId { id: u8::read(input)? })
+	}
+}
+
+impl Persistent for TileShape {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		self.get_id().write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let id = u8::read(input)?;
+		TileShape::from_id(id).ok_or_else(|| invalid_data(format!("Unknown tile shape {}", id)))
+	}
+}
+
+impl Persistent for Liquid {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		self.kind.get_id().write(out)?;
+		self.amount.write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let id = u8::read(input)?;
+		let kind = LiquidKind::from_id(id).ok_or_else(|| invalid_data(format!("Unknown liquid {}", id)))?;
+		Ok(Liquid::new(kind, u8::read(input)?))
+	}
+}
+
+// Neighbor matrices are never saved, they get recalculated when the chunk gets loaded.
+impl Persistent for Tile {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		self.id.write(out)?;
+		self.variance.write(out)?;
+		self.shape.write(out)?;
+		self.paint.write(out)?;
+		self.frame_x.write(out)?;
+		self.frame_y.write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let mut tile = Tile::id(TileId::read(input)?);
+		tile.variance = u8::read(input)?;
+		tile.shape = TileShape::read(input)?;
+		tile.paint = PaintId::read(input)?;
+		tile.frame_x = u8::read(input)?;
+		tile.frame_y = u8::read(input)?;
+		Ok(tile)
+	}
+}
+
+impl Persistent for Wall {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		self.id.write(out)?;
+		self.variance.write(out)?;
+		self.paint.write(out)
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let mut wall = Wall::id(WallId::read(input)?);
+		wall.variance = u8::read(input)?;
+		wall.paint = PaintId::read(input)?;
+		Ok(wall)
+	}
+}
+
+impl Persistent for Chunk {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		CHUNK_VERSION.write(out)?;
+		for row in Grid::<Tile>::get_grid(self) {
+			for tile in row {
+				tile.write(out)?;
+			}
+		}
+		for row in Grid::<Wall>::get_grid(self) {
+			for wall in row {
+				wall.write(out)?;
+			}
+		}
+		for row in Grid::<Liquid>::get_grid(self) {
+			for liquid in row {
+				liquid.write(out)?;
+			}
+		}
+
+		// Sorted so the same chunk always gets written the same way.
+		let mut tile_entities: Vec<_> = self.tile_entities.iter().collect();
+		tile_entities.sort_by_key(|(pos, _)| (pos.y, pos.x));
+		(tile_entities.len() as u16).write(out)?;
+		for (pos, entity) in tile_entities {
+			pos.x.write(out)?;
+			pos.y.write(out)?;
+			entity.write(out)?;
+		}
+		Ok(())
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let version = u8::read(input)?;
+		if version != CHUNK_VERSION {
+			return Err(invalid_data(format!("Chunk version {} is not supported, expected {}", version, CHUNK_VERSION)));
+		}
+
+		let mut chunk = Chunk::default();
+		for y in 0..CHUNK_SIZE {
+			for x in 0..CHUNK_SIZE {
+				Grid::<Tile>::get_grid_mut(&mut chunk)[y][x] = Tile::read(input)?;
+			}
+		}
+		for y in 0..CHUNK_SIZE {
+			for x in 0..CHUNK_SIZE {
+				Grid::<Wall>::get_grid_mut(&mut chunk)[y][x] = Wall::read(input)?;
+			}
+		}
+		for y in 0..CHUNK_SIZE {
+			for x in 0..CHUNK_SIZE {
+				Grid::<Liquid>::get_grid_mut(&mut chunk)[y][x] = Liquid::read(input)?;
+			}
+		}
+
+		for _ in 0..u16::read(input)? {
+			let (x, y) = (u8::read(input)?, u8::read(input)?);
+			if x as usize >= CHUNK_SIZE || y as usize >= CHUNK_SIZE {
+				return Err(invalid_data(format!("Tile entity at {} {} is outside of the chunk", x, y)));
+			}
+			chunk.tile_entities.insert(ChunkSubPos::new(x, y), TileEntity::read(input)?);
+		}
+
+		NeighborMatrix::update_grid(Grid::<Tile>::get_grid_mut(&mut chunk));
+		NeighborMatrix::update_grid(Grid::<Wall>::get_grid_mut(&mut chunk));
+		NeighborMatrix::update_grid(Grid::<Liquid>::get_grid_mut(&mut chunk));
+		Ok(chunk)
+	}
+}
+
+impl Persistent for PendingEdit {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		let pos = self.get_pos();
+		self.get_layer().write(out)?;
+		pos.x.write(out)?;
+		pos.y.write(out)?;
+		match self {
+			PendingEdit::Tile(_, tile) => tile.write(out),
+			PendingEdit::Wall(_, wall) => wall.write(out),
+			PendingEdit::Liquid(_, liquid) => liquid.write(out),
+		}
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let layer = u8::read(input)?;
+		let (x, y) = (u8::read(input)?, u8::read(input)?);
+		if x as usize >= CHUNK_SIZE || y as usize >= CHUNK_SIZE {
+			return Err(invalid_data(format!("Pending edit at {} {} is outside of the chunk", x, y)));
+		}
+
+		let pos = ChunkSubPos::new(x, y);
+		match layer {
+			0 => Ok(PendingEdit::Tile(pos, Tile::read(input)?)),
+			1 => Ok(PendingEdit::Wall(pos, Wall::read(input)?)),
+			2 => Ok(PendingEdit::Liquid(pos, Liquid::read(input)?)),
+			_ => Err(invalid_data(format!("Unknown layer {}", layer))),
+		}
+	}
+}
+
+/// A world save on disk. Every chunk is stored in its own file.
+pub struct WorldSave {
+	dir: PathBuf,
+}
+
+impl WorldSave {
+	pub fn new(dir: PathBuf) -> WorldSave {
+		Self { dir }
+	}
+
+	fn get_chunk_path(&self, pos: &ChunkPos) -> PathBuf {
+		self.dir.join(format!("chunk.{}.{}.bin", pos.x, pos.y))
+	}
+
+	pub fn has_chunk(&self, pos: &ChunkPos) -> bool {
+		self.get_chunk_path(pos).exists()
+	}
+
+	/// Writes the chunk together with its scheduled updates. Updates are stored as the ticks left until they are due.
+	pub fn save_chunk(&self, pos: &ChunkPos, chunk: &Chunk, updates: &[(ChunkSubPos, u64)]) -> Result<()> {
+		create_dir_all(&self.dir)?;
+		let mut out = BufWriter::new(File::create(self.get_chunk_path(pos))?);
+		chunk.write(&mut out)?;
+		(updates.len() as u32).write(&mut out)?;
+		for (sub_pos, delay) in updates {
+			sub_pos.x.write(&mut out)?;
+			sub_pos.y.write(&mut out)?;
+			delay.write(&mut out)?;
+		}
+		out.flush()
+	}
+
+	/// Writes the edits that wait for chunks which are not generated yet.
+	pub fn save_pending_edits(&self, edits: &HashMap<ChunkPos, PendingEdits>) -> Result<()> {
+		create_dir_all(&self.dir)?;
+		let mut out = BufWriter::new(File::create(self.dir.join("pending.bin"))?);
+		(edits.len() as u32).write(&mut out)?;
+		for (pos, edits) in edits {
+			pos.x.write(&mut out)?;
+			pos.y.write(&mut out)?;
+			(edits.len() as u32).write(&mut out)?;
+			for edit in edits.values() {
+				edit.write(&mut out)?;
+			}
+		}
+		out.flush()
+	}
+
+	/// Reads the pending edits of the save. A save without any gives an empty map.
+	/// A file that can not be read gets moved to `pending.corrupt.bin`, so saving again does not overwrite it.
+	pub fn load_pending_edits(&self) -> Result<HashMap<ChunkPos, PendingEdits>> {
+		let path = self.dir.join("pending.bin");
+		if !path.exists() {
+			return Ok(HashMap::new());
+		}
+
+		let result = Self::read_pending_edits(&path);
+		if result.is_err() {
+			rename(&path, self.dir.join("pending.corrupt.bin"))?;
+		}
+		result
+	}
+
+	fn read_pending_edits(path: &Path) -> Result<HashMap<ChunkPos, PendingEdits>> {
+		let mut out = HashMap::new();
+		let mut input = BufReader::new(File::open(path)?);
+		for _ in 0..u32::read(&mut input)? {
+			let pos = ChunkPos::new(i16::read(&mut input)?, u16::read(&mut input)?);
+			let len = u32::read(&mut input)?;
+			if len as usize > MAX_PENDING_EDITS {
+				return Err(invalid_data(format!("Chunk {:?} has {} pending edits but only room for {}", pos, len, MAX_PENDING_EDITS)));
+			}
+
+			let mut edits = PendingEdits::new();
+			for _ in 0..len {
+				let edit = PendingEdit::read(&mut input)?;
+				edits.insert(edit.get_key(), edit);
+			}
+			out.insert(pos, edits);
+		}
+		Ok(out)
+	}
+
+	fn get_player_path(&self, id: usize) -> PathBuf {
+		self.dir.join(format!("player.{}.bin", id))
+	}
+
+	pub fn save_player(&self, id: usize, player: &Player) -> Result<()> {
+		create_dir_all(&self.dir)?;
+		let mut out = BufWriter::new(File::create(self.get_player_path(id))?);
+		player.write(&mut out)?;
+		out.flush()
+	}
+
+	/// Reads the player that joined as `id` last time. A save without that player gives None.
+	pub fn load_player(&self, id: usize) -> Result<Option<Player>> {
+		let path = self.get_player_path(id);
+		if !path.exists() {
+			return Ok(None);
+		}
+		Player::read(&mut BufReader::new(File::open(path)?)).map(Some)
+	}
+
+	pub fn load_chunk(&self, pos: &ChunkPos) -> Result<(Chunk, Vec<(ChunkSubPos, u64)>)> {
+		let mut input = BufReader::new(File::open(self.get_chunk_path(pos))?);
+		let chunk = Chunk::read(&mut input)?;
+		let len = u32::read(&mut input)?;
+		if len as usize > CHUNK_SIZE * CHUNK_SIZE {
+			return Err(invalid_data(format!("Chunk has {} scheduled updates but only {} positions", len, CHUNK_SIZE * CHUNK_SIZE)));
+		}
+		let mut updates = Vec::with_capacity(len as usize);
+		for _ in 0..len {
+			let (x, y) = (u8::read(&mut input)?, u8::read(&mut input)?);
+			if x as usize >= CHUNK_SIZE || y as usize >= CHUNK_SIZE {
+				return Err(invalid_data(format!("Scheduled update at {} {} is outside of the chunk", x, y)));
+			}
+			updates.push((ChunkSubPos::new(x, y), u64::read(&mut input)?));
+		}
+		Ok((chunk, updates))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::{tile, World};
+	use crate::world::tile_entity::Sign;
+
+	fn create_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("rustaria-{}-{}", name, std::process::id()));
+		create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	#[test]
+	fn chunk_keeps_tile_shapes() {
+		let mut chunk = Chunk::default();
+		let shapes = [TileShape::Full, TileShape::Half, TileShape::SlopeBottomRight, TileShape::SlopeBottomLeft, TileShape::SlopeTopRight, TileShape::SlopeTopLeft];
+		for (x, shape) in shapes.iter().enumerate() {
+			let mut stone = Tile::id(tile::STONE);
+			stone.shape = *shape;
+			Grid::<Tile>::set(&mut chunk, &ChunkSubPos::new(x as u8, 0), stone);
+		}
+
+		let mut bytes = Vec::new();
+		chunk.write(&mut bytes).unwrap();
+		let read = Chunk::read(&mut bytes.as_slice()).unwrap();
+		for (x, shape) in shapes.iter().enumerate() {
+			let tile: &Tile = read.get(&ChunkSubPos::new(x as u8, 0));
+			assert_eq!(tile.shape, *shape);
+			assert_eq!(tile.id, tile::STONE);
+		}
+	}
+
+	#[test]
+	fn tile_entities_get_written_in_order() {
+		let create = |positions: &mut dyn Iterator<Item = u8>| {
+			let mut chunk = Chunk::default();
+			for x in positions {
+				chunk.tile_entities.insert(ChunkSubPos::new(x, x / 2), TileEntity::Sign(Sign { text: x.to_string() }));
+			}
+			let mut bytes = Vec::new();
+			chunk.write(&mut bytes).unwrap();
+			bytes
+		};
+
+		let bytes = create(&mut (0..CHUNK_SIZE as u8));
+		assert_eq!(bytes, create(&mut (0..CHUNK_SIZE as u8).rev()));
+		let mut written = Vec::new();
+		Chunk::read(&mut bytes.as_slice()).unwrap().write(&mut written).unwrap();
+		assert_eq!(bytes, written);
+	}
+
+	#[test]
+	fn cut_off_string_is_rejected() {
+		let mut bytes = Vec::new();
+		u32::MAX.write(&mut bytes).unwrap();
+		bytes.extend_from_slice(b"short");
+		assert_eq!(String::read(&mut bytes.as_slice()).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+	}
+
+	#[test]
+	fn too_many_pending_edits_are_rejected() {
+		let dir = std::env::temp_dir().join(format!("rustaria-pending-{}", std::process::id()));
+		create_dir_all(&dir).unwrap();
+		let mut bytes = Vec::new();
+		1u32.write(&mut bytes).unwrap();
+		0i16.write(&mut bytes).unwrap();
+		0u16.write(&mut bytes).unwrap();
+		u32::MAX.write(&mut bytes).unwrap();
+		std::fs::write(dir.join("pending.bin"), bytes).unwrap();
+
+		let result = WorldSave::new(dir.clone()).load_pending_edits();
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert!(matches!(result, Err(error) if error.kind() == ErrorKind::InvalidData));
+	}
+
+	#[test]
+	fn players_get_saved_by_id() {
+		let dir = std::env::temp_dir().join(format!("rustaria-player-{}", std::process::id()));
+		let save = WorldSave::new(dir.clone());
+		let mut player = Player::new();
+		player.pos_x = 7.5;
+		save.save_player(1, &player).unwrap();
+
+		let first = save.load_player(0);
+		let second = save.load_player(1);
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert!(first.unwrap().is_none());
+		assert_eq!(second.unwrap().map(|player| player.pos_x), Some(7.5));
+	}
+
+	#[test]
+	fn unknown_shape_is_rejected() {
+		assert!(TileShape::read(&mut [200u8].as_slice()).is_err());
+	}
+
+	#[test]
+	fn unreadable_pending_edits_keep_the_save() {
+		let dir = create_dir("corrupt-pending");
+		std::fs::write(dir.join("pending.bin"), [1, 0]).unwrap();
+
+		let mut world = World::new(0);
+		let result = world.set_save(WorldSave::new(dir.clone()));
+		let moved = dir.join("pending.corrupt.bin").exists() && !dir.join("pending.bin").exists();
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert!(result.is_err());
+		assert!(world.save.is_some());
+		assert!(moved);
+	}
+
+	#[test]
+	fn chunks_only_unload_into_a_save() {
+		let pos = ChunkPos::new(0, 0);
+		let mut world = create_world(vec![(pos, create_chunk(|_, _| tile::STONE))]);
+		let id = world.player_join(crate::player::Player::new());
+		world.acquire_player_mut(&id).pos_x = 10000.0;
+
+		world.unload_far_chunks().unwrap();
+		assert!(world.get_chunk(&pos).is_some());
+
+		let dir = create_dir("unload");
+		world.set_save(WorldSave::new(dir.clone())).unwrap();
+		world.unload_far_chunks().unwrap();
+		let saved = WorldSave::new(dir.clone()).has_chunk(&pos);
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert!(world.get_chunk(&pos).is_none());
+		assert!(saved);
+	}
+
+	#[test]
+	fn corrupt_chunk_gets_generated_and_reported_once() {
+		let dir = create_dir("corrupt-chunk");
+		let pos = ChunkPos::new(0, 0);
+		let save = WorldSave::new(dir.clone());
+		std::fs::write(save.get_chunk_path(&pos), [255]).unwrap();
+		let mut world = World::new(0);
+		world.set_save(save).unwrap();
+
+		let first = world.load_chunks(vec![pos]);
+		let second = world.load_chunks(vec![pos]);
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert!(first.is_err());
+		assert!(second.is_ok());
+
+		let start = std::time::Instant::now();
+		while world.get_chunk(&pos).is_none() {
+			assert!(start.elapsed().as_secs() < 10, "Chunk did not generate");
+			for (pos, chunk) in world.chunk_generator.generate_chunks().unwrap_or_default() {
+				world.insert_chunk(pos, chunk);
+			}
+		}
+		// Once it arrived the chunk can be generated again after it got unloaded.
+		assert!(!world.chunk_generator.is_queued(&pos));
+	}
+}
@@ -0,0 +1,258 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Result, Write};
+use std::path::Path;
+
+use crate::misc::pos::{ChunkPos, WorldPos};
+use crate::misc::util::CHUNK_SIZE;
+use crate::world::{Chunk, Grid, World};
+use crate::world::liquid::Liquid;
+use crate::world::paint::PaintId;
+use crate::world::region::{Clipboard, ClipboardCell, Region, Transform};
+use crate::world::save::{invalid_data, Persistent};
+use crate::world::tile::{Tile, TileId, TileShape};
+use crate::world::tile_entity::TileEntity;
+use crate::world::wall::{Wall, WallId};
+
+/// Bump this every time the layout of a schematic file changes.
+const SCHEMATIC_VERSION: u8 = 2;
+/// Schematics can be at most this many tiles wide and high.
+const MAX_SCHEMATIC_SIZE: u32 = 1024;
+
+/// A structure that can be saved to a file and placed into worlds again.
+/// Tiles and walls are stored through a palette of their keys so schematics survive id changes.
+#[derive(Clone)]
+pub struct Schematic {
+	pub name: String,
+	clipboard: Clipboard,
+}
+
+impl Schematic {
+	pub fn new(name: String, clipboard: Clipboard) -> Schematic {
+		Self { name, clipboard }
+	}
+
+	/// Copies a region of the world into a new schematic.
+	pub fn export(world: &World, region: &Region, name: String) -> Schematic {
+		Self::new(name, world.copy(region))
+	}
+
+	/// Pastes the schematic into the world with its bottom left corner at `origin`.
+	pub fn import(&self, world: &mut World, origin: &WorldPos, transform: &Transform) -> usize {
+		world.paste(&self.clipboard, origin, transform)
+	}
+
+	pub fn get_clipboard(&self) -> &Clipboard {
+		&self.clipboard
+	}
+
+	pub fn save(&self, path: &Path) -> Result<()> {
+		let mut out = BufWriter::new(File::create(path)?);
+		self.write(&mut out)?;
+		out.flush()
+	}
+
+	pub fn load(path: &Path) -> Result<Schematic> {
+		Schematic::read(&mut BufReader::new(File::open(path)?))
+	}
+
+	/// Writes the part of the schematic that overlaps the chunk straight into it. Used by the world generator,
+	/// so neighbors are left for the generator to calculate.
+	pub fn place_in_chunk(&self, chunk: &mut Chunk, pos: &ChunkPos, origin: &WorldPos) {
+		// Structures that do not fit into the world do not get placed.
+		let region = match Region::from_size(origin, self.clipboard.get_width(), self.clipboard.get_height()) {
+			Some(region) => region,
+			None => return,
+		};
+		for y in 0..CHUNK_SIZE as u8 {
+			for x in 0..CHUNK_SIZE as u8 {
+				let world_pos = WorldPos::from_chunk(pos, x, y);
+				if !region.contains(&world_pos) {
+					continue;
+				}
+
+				let local_x = (world_pos.get_x() as i64 - origin.get_x() as i64) as u32;
+				let local_y = world_pos.get_y() - origin.get_y();
+				if let Some(cell) = self.clipboard.get(local_x, local_y) {
+					let sub_pos = world_pos.get_chunk_sub_pos();
+					chunk.set(sub_pos, cell.tile);
+					chunk.set(sub_pos, cell.wall);
+					chunk.set(sub_pos, cell.liquid);
+					// Freshly generated chunks have no contents worth dropping yet.
+					let _ = chunk.sync_tile_entity(sub_pos);
+					if let (Some(entity), Some(target)) = (self.clipboard.get_tile_entity(local_x, local_y), chunk.get_tile_entity_mut(sub_pos)) {
+						*target = entity.clone();
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Gets the index of `key` in the palette, adding it if it is new.
+fn get_palette_index(palette: &mut Vec<&'static str>, key: &'static str) -> u16 {
+	match palette.iter().position(|existing| *existing == key) {
+		Some(index) => index as u16,
+		None => {
+			palette.push(key);
+			(palette.len() - 1) as u16
+		}
+	}
+}
+
+fn read_palette<R: Read, T>(input: &mut R, from_key: fn(&str) -> Option<T>) -> Result<Vec<T>> {
+	let len = u16::read(input)?;
+	let mut out = Vec::with_capacity(len as usize);
+	for _ in 0..len {
+		let key = String::read(input)?;
+		out.push(from_key(&key).ok_or_else(|| invalid_data(format!("Unknown key {}", key)))?);
+	}
+	Ok(out)
+}
+
+fn get_palette_entry<T: Copy>(palette: &[T], index: u16) -> Result<T> {
+	palette.get(index as usize).copied().ok_or_else(|| invalid_data(format!("Palette index {} is out of range", index)))
+}
+
+// Layout: version, name, size, tile palette, wall palette, cells and then the tile entities.
+// Cells go row by row from the bottom left, every cell starts with a byte telling if it holds anything.
+impl Persistent for Schematic {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		let (width, height) = (self.clipboard.get_width(), self.clipboard.get_height());
+		let mut tiles = Vec::new();
+		let mut walls = Vec::new();
+		let mut cells = Vec::new();
+		for y in 0..height {
+			for x in 0..width {
+				if let Some(cell) = self.clipboard.get(x, y) {
+					let tile_key = cell.tile.id.get_key().ok_or_else(|| invalid_data(format!("Tile {} has no key", cell.tile.id.id)))?;
+					let wall_key = cell.wall.id.get_key().ok_or_else(|| invalid_data(format!("Wall {} has no key", cell.wall.id.id)))?;
+					cells.push(Some((get_palette_index(&mut tiles, tile_key), get_palette_index(&mut walls, wall_key), cell)));
+				} else {
+					cells.push(None);
+				}
+			}
+		}
+
+		SCHEMATIC_VERSION.write(out)?;
+		self.name.write(out)?;
+		width.write(out)?;
+		height.write(out)?;
+		for palette in [&tiles, &walls] {
+			(palette.len() as u16).write(out)?;
+			for key in palette {
+				key.to_string().write(out)?;
+			}
+		}
+
+		for cell in cells {
+			match cell {
+				Some((tile, wall, cell)) => {
+					1u8.write(out)?;
+					tile.write(out)?;
+					cell.tile.variance.write(out)?;
+					cell.tile.shape.write(out)?;
+					cell.tile.paint.write(out)?;
+					cell.tile.frame_x.write(out)?;
+					cell.tile.frame_y.write(out)?;
+					wall.write(out)?;
+					cell.wall.variance.write(out)?;
+					cell.wall.paint.write(out)?;
+					cell.liquid.write(out)?;
+				}
+				None => 0u8.write(out)?,
+			}
+		}
+
+		(self.clipboard.get_tile_entities().len() as u16).write(out)?;
+		for ((x, y), entity) in self.clipboard.get_tile_entities() {
+			x.write(out)?;
+			y.write(out)?;
+			entity.write(out)?;
+		}
+		Ok(())
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		let version = u8::read(input)?;
+		if version != SCHEMATIC_VERSION {
+			return Err(invalid_data(format!("Unsupported schematic version {}", version)));
+		}
+
+		let name = String::read(input)?;
+		let (width, height) = (u32::read(input)?, u32::read(input)?);
+		if width == 0 || height == 0 || width > MAX_SCHEMATIC_SIZE || height > MAX_SCHEMATIC_SIZE {
+			return Err(invalid_data(format!("Invalid schematic size {}x{}", width, height)));
+		}
+		let tiles = read_palette(input, TileId::from_key)?;
+		let walls = read_palette(input, WallId::from_key)?;
+
+		let mut clipboard = Clipboard::new(width, height);
+		for y in 0..height {
+			for x in 0..width {
+				if u8::read(input)? == 0 {
+					continue;
+				}
+
+				let mut tile = Tile::id(get_palette_entry(&tiles, u16::read(input)?)?);
+				tile.variance = u8::read(input)?;
+				tile.shape = TileShape::read(input)?;
+				tile.paint = PaintId::read(input)?;
+				tile.frame_x = u8::read(input)?;
+				tile.frame_y = u8::read(input)?;
+				let mut wall = Wall::id(get_palette_entry(&walls, u16::read(input)?)?);
+				wall.variance = u8::read(input)?;
+				wall.paint = PaintId::read(input)?;
+				let liquid = Liquid::read(input)?;
+				clipboard.set(x, y, Some(ClipboardCell { tile, wall, liquid }));
+			}
+		}
+
+		for _ in 0..u16::read(input)? {
+			let (x, y) = (u32::read(input)?, u32::read(input)?);
+			if x >= width || y >= height {
+				return Err(invalid_data(format!("Tile entity at {} {} is outside of the schematic", x, y)));
+			}
+			clipboard.set_tile_entity(x, y, TileEntity::read(input)?);
+		}
+		Ok(Schematic { name, clipboard })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::ErrorKind;
+
+	use super::*;
+	use crate::world::tile::STONE;
+
+	fn read_with_size(width: u32, height: u32) -> Result<Schematic> {
+		let mut data = Vec::new();
+		SCHEMATIC_VERSION.write(&mut data).unwrap();
+		"test".to_string().write(&mut data).unwrap();
+		width.write(&mut data).unwrap();
+		height.write(&mut data).unwrap();
+		Schematic::read(&mut data.as_slice())
+	}
+
+	#[test]
+	fn invalid_sizes_are_rejected() {
+		for (width, height) in [(0, 4), (4, 0), (MAX_SCHEMATIC_SIZE + 1, 1), (1, u32::MAX)] {
+			let result = read_with_size(width, height);
+			assert!(matches!(result, Err(error) if error.kind() == ErrorKind::InvalidData));
+		}
+	}
+
+	#[test]
+	fn schematics_survive_a_round_trip() {
+		let mut clipboard = Clipboard::new(2, 1);
+		clipboard.set(1, 0, Some(ClipboardCell { tile: Tile::id(STONE), wall: Wall::air(), liquid: Liquid::air() }));
+		let mut data = Vec::new();
+		Schematic::new("test".to_string(), clipboard).write(&mut data).unwrap();
+
+		let schematic = Schematic::read(&mut data.as_slice()).unwrap();
+		let clipboard = schematic.get_clipboard();
+		assert_eq!((clipboard.get_width(), clipboard.get_height()), (2, 1));
+		assert!(clipboard.get(0, 0).is_none());
+		assert_eq!(clipboard.get(1, 0).unwrap().tile.id, STONE);
+	}
+}
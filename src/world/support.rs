@@ -0,0 +1,118 @@
+use crate::item::stack::ItemStack;
+use crate::misc::pos::WorldPos;
+use crate::misc::util::Direction;
+use crate::world::{tile, wall, World};
+use crate::world::tile::Tile;
+use crate::world::wall::Wall;
+
+/// What a tile needs around it to stay in place.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SupportRule {
+	/// Floats happily on its own.
+	None,
+	SolidBelow,
+	SolidAbove,
+	WallBehind,
+	/// Any tile next to it or a wall behind it.
+	AnySide,
+}
+
+impl SupportRule {
+	pub fn is_satisfied(&self, world: &World, pos: &WorldPos) -> bool {
+		match self {
+			SupportRule::None => true,
+			SupportRule::SolidBelow => Self::is_solid(world, pos, Direction::Down),
+			SupportRule::SolidAbove => Self::is_solid(world, pos, Direction::Top),
+			SupportRule::WallBehind => Self::has_wall(world, pos),
+			SupportRule::AnySide => Self::has_wall(world, pos) || Direction::iter().iter().any(|dir| Self::is_solid(world, pos, *dir)),
+		}
+	}
+
+	// Unloaded chunks and the world border count as support so tiles do not break when their neighbor is not loaded.
+	fn is_solid(world: &World, pos: &WorldPos, direction: Direction) -> bool {
+		match pos.shift(direction) {
+			Some(neighbor) => !matches!(world.get::<Tile>(&neighbor), Some(tile) if tile.id == tile::AIR),
+			None => true,
+		}
+	}
+
+	fn has_wall(world: &World, pos: &WorldPos) -> bool {
+		!matches!(world.get::<Wall>(pos), Some(wall) if wall.id == wall::AIR)
+	}
+}
+
+/// Items that came out of the world, like a broken tile or the contents of a broken chest.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ItemDrop {
+	pub pos: WorldPos,
+	pub stack: ItemStack,
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::item::ItemId;
+	use crate::item::stack::ItemStack;
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::world::mining::MineResult;
+	use crate::world::support::ItemDrop;
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile::{self, Tile, TileId};
+	use crate::world::World;
+
+	fn create_hanging_world(tiles: &'static [((u8, u8), TileId)]) -> World {
+		create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| {
+			tiles.iter().find(|(pos, _)| *pos == (x, y)).map_or(tile::AIR, |(_, id)| *id)
+		}))])
+	}
+
+	fn mine_until_broken(world: &mut World, pos: &WorldPos) {
+		for _ in 0..100 {
+			if world.mine(pos, 100) == MineResult::Broken {
+				return;
+			}
+		}
+		panic!("{:?} never broke", pos);
+	}
+
+	fn get_id(world: &World, x: i32, y: u32) -> TileId {
+		world.get::<Tile>(&WorldPos::new(x, y)).unwrap().id
+	}
+
+	#[test]
+	fn vines_break_when_the_grass_above_is_mined() {
+		let mut world = create_hanging_world(&[
+			((5, 10), tile::GRASS), ((5, 9), tile::REGULAR_VINE),
+			((7, 10), tile::STONE), ((7, 9), tile::REGULAR_VINE),
+		]);
+		mine_until_broken(&mut world, &WorldPos::new(5, 10));
+		assert_eq!(get_id(&world, 5, 10), tile::AIR);
+		assert_eq!(get_id(&world, 5, 9), tile::AIR);
+		assert_eq!(get_id(&world, 7, 9), tile::REGULAR_VINE);
+	}
+
+	#[test]
+	fn breaks_cascade_down_a_vine_chain_with_one_drop_per_tile() {
+		// A cobweb that only holds on to the lowest vine.
+		let mut world = create_hanging_world(&[
+			((10, 10), tile::GRASS),
+			((10, 9), tile::REGULAR_VINE),
+			((10, 8), tile::REGULAR_VINE),
+			((10, 7), tile::REGULAR_VINE),
+			((10, 6), tile::REGULAR_VINE),
+			((11, 6), tile::COBWEB),
+		]);
+		mine_until_broken(&mut world, &WorldPos::new(10, 10));
+		for y in 6..=10 {
+			assert_eq!(get_id(&world, 10, y), tile::AIR, "Tile at y {} did not break", y);
+		}
+		assert_eq!(get_id(&world, 11, 6), tile::AIR);
+
+		// Vines do not drop anything, the grass and the cobweb drop once each.
+		let mut drops = world.get_drops().to_vec();
+		drops.sort_by_key(|drop| drop.pos.get_y());
+		assert_eq!(drops, vec![
+			ItemDrop { pos: WorldPos::new(11, 6), stack: ItemStack::new(ItemId::Block(tile::COBWEB), 1) },
+			ItemDrop { pos: WorldPos::new(10, 10), stack: ItemStack::new(ItemId::Block(tile::DIRT), 1) },
+		]);
+	}
+}
@@ -0,0 +1,23 @@
+use crate::misc::pos::{ChunkPos, ChunkSubPos};
+use crate::world::{Chunk, Grid, World};
+use crate::world::neighbor::NeighborMatrix;
+use crate::world::tile::{Tile, TileId};
+
+/// A chunk with the tile `tile(x, y)` returns at every position and its inner neighbors already calculated.
+pub fn create_chunk(tile: impl Fn(u8, u8) -> TileId) -> Chunk {
+	let mut chunk = Chunk::default();
+	for pos in ChunkSubPos::iter() {
+		Grid::<Tile>::set(&mut chunk, &pos, Tile::id(tile(pos.x, pos.y)));
+	}
+	NeighborMatrix::update_grid(Grid::<Tile>::get_grid_mut(&mut chunk));
+	chunk
+}
+
+/// A world without players that only has the given chunks loaded.
+pub fn create_world(chunks: Vec<(ChunkPos, Chunk)>) -> World {
+	let mut world = World::new(0);
+	for (pos, chunk) in chunks {
+		world.insert_chunk(pos, chunk);
+	}
+	world
+}
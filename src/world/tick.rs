@@ -1,23 +1,359 @@
-use crate::misc::util::CallbackResponse;
+use std::collections::{BTreeSet, HashMap};
 
+use crate::misc::pos::{ChunkPos, ChunkSubPos, WorldPos};
+use crate::misc::random::Random;
+use crate::misc::util::{CallbackResponse, Direction};
+use crate::world::tile;
+use crate::world::tile::{Tile, TileId};
+use crate::world::World;
+
+/// How many tiles of every loaded chunk get a random tick each world tick.
+pub const RANDOM_TICKS_PER_CHUNK: u32 = 3;
+/// How far corruption reaches from a corrupt tile on both axes.
+pub const CORRUPTION_RANGE: u32 = 2;
+
+/// State that keeps changing on its own, like a burning furnace. Returns `Stop` once it has nothing left to do.
 pub trait Tickable {
-    fn tick(&self) -> CallbackResponse;
+    fn tick(&mut self) -> CallbackResponse;
 }
 
+/// Gets called on a tile of its type when an update scheduled for it is due.
+pub type ScheduledTickFn = fn(&mut World, &WorldPos);
+
+/// Tile updates that should happen after a delay. Every position is only queued once, with its earliest due tick.
+/// Updates that are due on the same tick run ordered by position.
 #[derive(Default)]
-pub struct TickHandler<'a> {
-    data: Vec<&'a dyn Tickable>,
+pub struct ScheduledUpdates {
+    queue: BTreeSet<(u64, i32, u32)>,
+    due: HashMap<WorldPos, u64>,
+    handlers: HashMap<TileId, ScheduledTickFn>,
+}
+
+impl ScheduledUpdates {
+    /// Queues an update for `pos` on tick `due`. Returns false if the position already has an earlier or equal update queued.
+    pub fn schedule(&mut self, pos: &WorldPos, due: u64) -> bool {
+        if let Some(existing) = self.due.get(pos) {
+            if *existing <= due {
+                return false;
+            }
+            self.queue.remove(&(*existing, pos.get_x(), pos.get_y()));
+        }
+
+        self.due.insert(*pos, due);
+        self.queue.insert((due, pos.get_x(), pos.get_y()));
+        true
+    }
+
+    /// Removes and returns every update that is due at or before `tick` inside of a chunk that `is_loaded` accepts.
+    /// Updates of other chunks stay queued and run once their chunk is loaded again.
+    pub fn take_due(&mut self, tick: u64, is_loaded: impl Fn(&ChunkPos) -> bool) -> Vec<WorldPos> {
+        let due: Vec<(u64, i32, u32)> = self.queue.range(..=(tick, i32::MAX, u32::MAX))
+            .filter(|(_, x, y)| is_loaded(WorldPos::new(*x, *y).get_chunk_pos()))
+            .copied()
+            .collect();
+
+        let mut out = Vec::with_capacity(due.len());
+        for entry in due {
+            self.queue.remove(&entry);
+            let pos = WorldPos::new(entry.1, entry.2);
+            self.due.remove(&pos);
+            out.push(pos);
+        }
+        out
+    }
+
+    /// The updates queued inside of a chunk with their due tick.
+    pub fn get_chunk(&self, chunk: &ChunkPos) -> Vec<(ChunkSubPos, u64)> {
+        let mut out: Vec<(ChunkSubPos, u64)> = self.due.iter()
+            .filter(|(pos, _)| pos.get_chunk_pos() == chunk)
+            .map(|(pos, due)| (*pos.get_chunk_sub_pos(), *due))
+            .collect();
+        out.sort_by_key(|(pos, due)| (*due, pos.x, pos.y));
+        out
+    }
+
+    /// Removes and returns the updates queued inside of a chunk.
+    pub fn remove_chunk(&mut self, chunk: &ChunkPos) -> Vec<(ChunkSubPos, u64)> {
+        let out = self.get_chunk(chunk);
+        for (sub_pos, due) in &out {
+            let pos = WorldPos::from_chunk(chunk, sub_pos.x, sub_pos.y);
+            self.due.remove(&pos);
+            self.queue.remove(&(*due, pos.get_x(), pos.get_y()));
+        }
+        out
+    }
+
+    pub fn is_scheduled(&self, pos: &WorldPos) -> bool {
+        self.due.contains_key(pos)
+    }
+
+    pub fn len(&self) -> usize {
+        self.due.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.due.is_empty()
+    }
+
+    /// Replaces the handler of `id`.
+    pub fn register(&mut self, id: TileId, handler: ScheduledTickFn) {
+        self.handlers.insert(id, handler);
+    }
+
+    pub fn get_handler(&self, id: &TileId) -> Option<ScheduledTickFn> {
+        self.handlers.get(id).copied()
+    }
+}
+
+/// Gets called on a tile of its type that got randomly picked this tick.
+pub type RandomTickFn = fn(&mut World, &WorldPos, &mut Random);
+
+/// Picks random tiles in every loaded chunk and lets their type react to it. This is what makes grass spread.
+pub struct RandomTicker {
+    pub random: Random,
+    pub ticks_per_chunk: u32,
+    handlers: HashMap<TileId, RandomTickFn>,
+}
+
+impl RandomTicker {
+    pub fn new(seed: u64) -> RandomTicker {
+        let mut ticker = Self {
+            random: Random::new(seed),
+            ticks_per_chunk: RANDOM_TICKS_PER_CHUNK,
+            handlers: HashMap::new(),
+        };
+
+        ticker.register(tile::GRASS, grass_tick);
+        ticker.register(tile::JUNGLE_GRASS, grass_tick);
+        ticker.register(tile::MUSHROOM_GRASS, grass_tick);
+        ticker.register(tile::HALLOWED_GRASS, grass_tick);
+        ticker.register(tile::CORRUPT_GRASS, corruption_tick);
+        ticker.register(tile::REGULAR_VINE, vine_tick);
+        ticker.register(tile::JUNGLE_VINE, vine_tick);
+        ticker
+    }
+
+    /// Replaces the handler of `id`.
+    pub fn register(&mut self, id: TileId, handler: RandomTickFn) {
+        self.handlers.insert(id, handler);
+    }
+
+    pub fn get_handler(&self, id: &TileId) -> Option<RandomTickFn> {
+        self.handlers.get(id).copied()
+    }
+}
+
+fn get_relative(world: &World, pos: &WorldPos, x: i32, y: i32) -> Option<(WorldPos, Tile)> {
+    let pos = pos.checked_add(x, y)?;
+    world.get::<Tile>(&pos).map(|tile| (pos, *tile))
+}
+
+fn is_exposed(world: &World, pos: &WorldPos) -> bool {
+    Direction::iter().iter().any(|dir| {
+        matches!(pos.shift(*dir).and_then(|neighbor| world.get::<Tile>(&neighbor)), Some(tile) if tile.id == tile::AIR)
+    })
+}
+
+/// Converts the tile at `pos` keeping its shape and paint.
+fn convert(world: &mut World, pos: &WorldPos, tile: Tile, id: TileId) {
+    world.set(pos, Tile { id, ..tile });
+}
+
+/// The tile a grass type grows on and the vine it grows below itself.
+fn get_grass_soil(id: &TileId) -> Option<(TileId, Option<TileId>)> {
+    match *id {
+        tile::GRASS => Some((tile::DIRT, Some(tile::REGULAR_VINE))),
+        tile::HALLOWED_GRASS => Some((tile::DIRT, Some(tile::REGULAR_VINE))),
+        tile::CORRUPT_GRASS => Some((tile::DIRT, None)),
+        tile::JUNGLE_GRASS => Some((tile::MUD_BLOCK, Some(tile::JUNGLE_VINE))),
+        tile::MUSHROOM_GRASS => Some((tile::MUD_BLOCK, None)),
+        _ => None,
+    }
+}
+
+/// Spreads onto a random adjacent soil tile that touches air and grows vines below.
+fn grass_tick(world: &mut World, pos: &WorldPos, random: &mut Random) {
+    let grass = match world.get::<Tile>(pos) {
+        Some(tile) => *tile,
+        None => return,
+    };
+    let (soil, vine) = match get_grass_soil(&grass.id) {
+        Some(soil) => soil,
+        None => return,
+    };
+
+    let x = random.next_range(3) as i32 - 1;
+    let y = random.next_range(3) as i32 - 1;
+    if let Some((target_pos, target)) = get_relative(world, pos, x, y) {
+        if target.id == soil && is_exposed(world, &target_pos) {
+            convert(world, &target_pos, target, grass.id);
+        }
+    }
+
+    if let Some(vine) = vine {
+        if random.one_in(4) {
+            if let Some((below_pos, below)) = get_relative(world, pos, 0, -1) {
+                if below.id == tile::AIR {
+                    world.set(&below_pos, Tile::id(vine));
+                }
+            }
+        }
+    }
+}
+
+/// Vines grow downwards until they reach something.
+fn vine_tick(world: &mut World, pos: &WorldPos, random: &mut Random) {
+    let vine = match world.get::<Tile>(pos) {
+        Some(tile) => tile.id,
+        None => return,
+    };
+
+    if random.one_in(2) {
+        if let Some((below_pos, below)) = get_relative(world, pos, 0, -1) {
+            if below.id == tile::AIR {
+                world.set(&below_pos, Tile::id(vine));
+            }
+        }
+    }
+}
+
+/// Corrupts a random tile within `CORRUPTION_RANGE` that touches air and spreads like grass.
+/// Buried tiles stay clean, so corruption creeps along the surface instead of eating through the ground.
+fn corruption_tick(world: &mut World, pos: &WorldPos, random: &mut Random) {
+    let range = CORRUPTION_RANGE as i32;
+    let x = random.next_range(CORRUPTION_RANGE * 2 + 1) as i32 - range;
+    let y = random.next_range(CORRUPTION_RANGE * 2 + 1) as i32 - range;
+    if let Some((target_pos, target)) = get_relative(world, pos, x, y).filter(|(target_pos, _)| is_exposed(world, target_pos)) {
+        let corrupted = match target.id {
+            tile::GRASS => Some(tile::CORRUPT_GRASS),
+            tile::STONE => Some(tile::EBONSTONE),
+            tile::SAND => Some(tile::EBONSAND_BLOCK),
+            _ => None,
+        };
+        if let Some(corrupted) = corrupted {
+            convert(world, &target_pos, target, corrupted);
+        }
+    }
+
+    grass_tick(world, pos, random);
 }
 
-impl<'a> TickHandler<'a> {
-    pub fn tick(&mut self) {
-        self.data.retain(|tickable| match tickable.tick() {
-            CallbackResponse::Continue => true,
-            CallbackResponse::Stop => false,
-        });
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::testing::{create_chunk, create_world};
+
+    fn get_id(world: &World, x: i32, y: u32) -> TileId {
+        world.get::<Tile>(&WorldPos::new(x, y)).unwrap().id
+    }
+
+    /// Dirt up to y 10 with corrupt grass on top of it at x 10 and normal grass everywhere else.
+    fn create_hill() -> World {
+        create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| match (x, y) {
+            (10, 10) => tile::CORRUPT_GRASS,
+            (_, 10) => tile::GRASS,
+            (_, 0..=9) => tile::DIRT,
+            _ => tile::AIR,
+        }))])
+    }
+
+    #[test]
+    fn due_updates_wait_for_their_chunk() {
+        let mut updates = ScheduledUpdates::default();
+        let loaded = WorldPos::new(5, 5);
+        let unloaded = WorldPos::new(30, 5);
+        updates.schedule(&unloaded, 1);
+        updates.schedule(&loaded, 2);
+
+        assert_eq!(updates.take_due(5, |chunk| *chunk == ChunkPos::new(0, 0)), vec![loaded]);
+        assert!(updates.is_scheduled(&unloaded));
+        assert_eq!(updates.take_due(5, |_| true), vec![unloaded]);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn scheduled_update_runs_once_the_chunk_loads() {
+        let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, _| tile::AIR))]);
+        world.get_scheduled_updates_mut().register(tile::DIRT, |world, pos| world.set(pos, Tile::id(tile::STONE)));
+        let pos = WorldPos::new(30, 5);
+        world.schedule_update(&pos, 1);
+        for _ in 0..3 {
+            world.tick().unwrap();
+        }
+        assert!(world.get_scheduled_updates().is_scheduled(&pos));
+
+        world.insert_chunk(ChunkPos::new(1, 0), create_chunk(|_, _| tile::DIRT));
+        world.tick().unwrap();
+        assert_eq!(get_id(&world, 30, 5), tile::STONE);
+        assert!(world.get_scheduled_updates().is_empty());
     }
 
-    pub fn add_tickable(&mut self, tickable: &'a dyn Tickable) {
-        self.data.push(tickable);
+    #[test]
+    fn corruption_ids_are_unique() {
+        assert_ne!(tile::CORRUPT_GRASS, tile::DEMONITE_ORE);
+        assert_ne!(tile::EBONSTONE, tile::DEMONITE_ORE);
+        assert_ne!(tile::CORRUPT_GRASS, tile::EBONSTONE);
+    }
+
+    #[test]
+    fn random_ticks_are_reproducible() {
+        let mut first = create_hill();
+        let mut second = create_hill();
+        for _ in 0..2000 {
+            first.tick().unwrap();
+            second.tick().unwrap();
+        }
+
+        let mut corrupted = 0;
+        for pos in ChunkSubPos::iter() {
+            let pos = WorldPos::from_chunk(&ChunkPos::new(0, 0), pos.x, pos.y);
+            let id = first.get::<Tile>(&pos).unwrap().id;
+            assert_eq!(id, second.get::<Tile>(&pos).unwrap().id);
+            corrupted += (id == tile::CORRUPT_GRASS) as u32;
+        }
+        // Something has to happen for the comparison to mean anything.
+        assert!(corrupted > 1);
+    }
+
+    #[test]
+    fn corruption_spreads_along_the_surface_in_range() {
+        let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| match (x, y) {
+            (10, 10) => tile::CORRUPT_GRASS,
+            (_, 0..=10) => tile::STONE,
+            _ => tile::AIR,
+        }))]);
+        let mut random = Random::new(7);
+        for _ in 0..500 {
+            corruption_tick(&mut world, &WorldPos::new(10, 10), &mut random);
+        }
+
+        for x in 0..24 {
+            let expected = match x {
+                10 => tile::CORRUPT_GRASS,
+                8..=12 => tile::EBONSTONE,
+                _ => tile::STONE,
+            };
+            assert_eq!(get_id(&world, x, 10), expected, "Surface at x {}", x);
+            for y in 0..10 {
+                assert_eq!(get_id(&world, x, y), tile::STONE, "Buried tile at {} {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn corrupt_grass_spreads_onto_exposed_dirt() {
+        let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|x, y| match (x, y) {
+            (10, 10) => tile::CORRUPT_GRASS,
+            (_, 0..=10) => tile::DIRT,
+            _ => tile::AIR,
+        }))]);
+        let mut random = Random::new(3);
+        for _ in 0..100 {
+            grass_tick(&mut world, &WorldPos::new(10, 10), &mut random);
+        }
+
+        assert_eq!(get_id(&world, 9, 10), tile::CORRUPT_GRASS);
+        assert_eq!(get_id(&world, 11, 10), tile::CORRUPT_GRASS);
+        assert_eq!(get_id(&world, 10, 9), tile::DIRT);
     }
 }
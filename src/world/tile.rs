@@ -1,12 +1,20 @@
-use crate::misc::util::{CallbackResponse};
+use crate::misc::util::{CollisionType, Direction};
 use crate::world::neighbor::{NeighborAware, NeighborMatrix, NeighborType};
-use crate::world::tick::Tickable;
+use crate::world::object::ObjectType;
+use crate::world::paint;
+use crate::world::paint::PaintId;
+use crate::world::support::SupportRule;
 use crate::world::tile;
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Tile {
 	pub id: TileId,
 	pub variance: u8,
+	pub shape: TileShape,
+	pub paint: PaintId,
+	/// Offset of this tile from the bottom left of the multi tile object it belongs to.
+	pub frame_x: u8,
+	pub frame_y: u8,
 	pub matrix: NeighborMatrix
 }
 
@@ -15,6 +23,10 @@ impl Tile {
 		Self {
 			variance: 0,
 			id: TileId { id: 0 },
+			shape: TileShape::Full,
+			paint: paint::NONE,
+			frame_x: 0,
+			frame_y: 0,
 			matrix: NeighborMatrix::default()
 		}
 	}
@@ -23,18 +35,25 @@ impl Tile {
 		Self {
 			id,
 			variance: 0,
+			shape: TileShape::Full,
+			paint: paint::NONE,
+			frame_x: 0,
+			frame_y: 0,
 			matrix: NeighborMatrix::default()
 		}
 	}
 
-	pub fn get_id(&self) -> &TileId {
-		&self.id
+	/// A single part of a multi tile object.
+	pub fn part(id: TileId, frame_x: u8, frame_y: u8) -> Tile {
+		Self {
+			frame_x,
+			frame_y,
+			..Self::id(id)
+		}
 	}
-}
 
-impl Tickable for Tile {
-	fn tick(&self) -> CallbackResponse {
-		panic!("Cannot tick basic tile")
+	pub fn get_id(&self) -> &TileId {
+		&self.id
 	}
 }
 
@@ -43,18 +62,145 @@ impl NeighborAware for Tile {
 		&self.matrix
 	}
 
-	fn apply_neighbor(&self, neighbor: &Tile) -> NeighborType {
+	fn get_neighbor_matrix_mut(&mut self) -> &mut NeighborMatrix {
+		&mut self.matrix
+	}
+
+	fn apply_neighbor(&self, neighbor: &Tile, direction: Direction) -> NeighborType {
 		if neighbor.id == tile::AIR {
 			NeighborType::Air
+		} else if !self.shape.is_face_full(direction) || !neighbor.shape.is_face_full(direction.flip()) {
+			// Slopes and half blocks do not connect on their open sides.
+			NeighborType::Air
 		} else /*if neighbor.id == target.id*/ {
 			NeighborType::Same
 		}
 	}
 }
 
+/// The geometry of a tile. Slopes are named after the corner that is solid,
+/// `SlopeBottomRight` for example is a ramp going up to the right.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TileShape {
+	Full,
+	Half,
+	SlopeBottomRight,
+	SlopeBottomLeft,
+	SlopeTopRight,
+	SlopeTopLeft,
+}
+
+impl TileShape {
+	/// The shape a hammer turns this shape into.
+	pub fn hammer(&self) -> TileShape {
+		match self {
+			TileShape::Full => TileShape::Half,
+			TileShape::Half => TileShape::SlopeBottomRight,
+			TileShape::SlopeBottomRight => TileShape::SlopeBottomLeft,
+			TileShape::SlopeBottomLeft => TileShape::SlopeTopRight,
+			TileShape::SlopeTopRight => TileShape::SlopeTopLeft,
+			TileShape::SlopeTopLeft => TileShape::Full,
+		}
+	}
+
+	/// If the side of the tile facing `direction` is completely covered.
+	pub fn is_face_full(&self, direction: Direction) -> bool {
+		match self {
+			TileShape::Full => true,
+			TileShape::Half => direction == Direction::Down,
+			TileShape::SlopeBottomRight => direction == Direction::Down || direction == Direction::Right,
+			TileShape::SlopeBottomLeft => direction == Direction::Down || direction == Direction::Left,
+			TileShape::SlopeTopRight => direction == Direction::Top || direction == Direction::Right,
+			TileShape::SlopeTopLeft => direction == Direction::Top || direction == Direction::Left,
+		}
+	}
+
+	/// The solid vertical span `(bottom, top)` of the tile at `x`. Both `x` and the result are in tile space (0..1).
+	pub fn get_solid_span(&self, x: f32) -> (f32, f32) {
+		let x = x.clamp(0.0, 1.0);
+		match self {
+			TileShape::Full => (0.0, 1.0),
+			TileShape::Half => (0.0, 0.5),
+			TileShape::SlopeBottomRight => (0.0, x),
+			TileShape::SlopeBottomLeft => (0.0, 1.0 - x),
+			TileShape::SlopeTopRight => (1.0 - x, 1.0),
+			TileShape::SlopeTopLeft => (x, 1.0),
+		}
+	}
+
+	/// The shape after turning the tile a quarter clockwise. Half blocks stay half blocks.
+	pub fn rotate_clockwise(&self) -> TileShape {
+		match self {
+			TileShape::Full => TileShape::Full,
+			TileShape::Half => TileShape::Half,
+			TileShape::SlopeBottomRight => TileShape::SlopeBottomLeft,
+			TileShape::SlopeBottomLeft => TileShape::SlopeTopLeft,
+			TileShape::SlopeTopLeft => TileShape::SlopeTopRight,
+			TileShape::SlopeTopRight => TileShape::SlopeBottomRight,
+		}
+	}
+
+	/// The shape mirrored left to right.
+	pub fn mirror_x(&self) -> TileShape {
+		match self {
+			TileShape::Full => TileShape::Full,
+			TileShape::Half => TileShape::Half,
+			TileShape::SlopeBottomRight => TileShape::SlopeBottomLeft,
+			TileShape::SlopeBottomLeft => TileShape::SlopeBottomRight,
+			TileShape::SlopeTopRight => TileShape::SlopeTopLeft,
+			TileShape::SlopeTopLeft => TileShape::SlopeTopRight,
+		}
+	}
+
+	/// The shape mirrored top to bottom. Half blocks stay on the bottom.
+	pub fn mirror_y(&self) -> TileShape {
+		match self {
+			TileShape::Full => TileShape::Full,
+			TileShape::Half => TileShape::Half,
+			TileShape::SlopeBottomRight => TileShape::SlopeTopRight,
+			TileShape::SlopeBottomLeft => TileShape::SlopeTopLeft,
+			TileShape::SlopeTopRight => TileShape::SlopeBottomRight,
+			TileShape::SlopeTopLeft => TileShape::SlopeBottomLeft,
+		}
+	}
+
+	/// The solid span over the part of the tile between `from` and `to`. The lowest bottom and highest top of that part.
+	pub fn get_solid_span_over(&self, from: f32, to: f32) -> (f32, f32) {
+		let (from_bottom, from_top) = self.get_solid_span(from);
+		let (to_bottom, to_top) = self.get_solid_span(to);
+		(from_bottom.min(to_bottom), from_top.max(to_top))
+	}
+
+	pub fn get_id(&self) -> u8 {
+		match self {
+			TileShape::Full => 0,
+			TileShape::Half => 1,
+			TileShape::SlopeBottomRight => 2,
+			TileShape::SlopeBottomLeft => 3,
+			TileShape::SlopeTopRight => 4,
+			TileShape::SlopeTopLeft => 5,
+		}
+	}
+
+	pub fn from_id(id: u8) -> Option<TileShape> {
+		match id {
+			0 => Some(TileShape::Full),
+			1 => Some(TileShape::Half),
+			2 => Some(TileShape::SlopeBottomRight),
+			3 => Some(TileShape::SlopeBottomLeft),
+			4 => Some(TileShape::SlopeTopRight),
+			5 => Some(TileShape::SlopeTopLeft),
+			_ => None,
+		}
+	}
+}
+
 macro_rules! pub_const_tile_id {
     ($($NAME:ident $ID:expr;)*) => {
         $(pub const $NAME: TileId = TileId { id: $ID };)*
+
+        /// Every named tile id with the key it gets saved as in files that outlive id changes.
+        pub const TILE_KEYS: &[(&str, TileId)] = &[$((stringify!($NAME), $NAME),)*];
     };
 }
 
@@ -69,9 +215,18 @@ pub_const_tile_id! {
     COPPER_ORE 8;
     GOLD_ORE 9;
     SILVER_ORE 10;
+    DOOR 11;
+    TABLE 15;
+    CHAIR 16;
+    ANVIL 17;
+    FURNACE 18;
+    WORK_BENCH 19;
+    PLATFORM 20;
+    CHEST 22;
     DEMONITE_ORE 23;
-    CORRUPT_GRASS 23;
-    EBONSTONE 23;
+    CORRUPT_GRASS 24;
+    EBONSTONE 26;
+    DEMON_ALTAR 27;
     WOOD 31;
     METEORITE 38;
     GRAY_BRICK 39;
@@ -89,6 +244,7 @@ pub_const_tile_id! {
     REGULAR_VINE 53;
     SAND 54;
     GLASS 55;
+    SIGN 56;
     OBSIDIAN 57;
     ASH_BLOCK 58;
     HELLSTONE 59;
@@ -134,3 +290,68 @@ pub_const_tile_id! {
 pub struct TileId {
 	pub id: u32,
 }
+
+impl TileId {
+	/// The key of this id, the name of its constant. Every named id has its own number, so ids and keys map one to one.
+	pub fn get_key(&self) -> Option<&'static str> {
+		TILE_KEYS.iter().find(|(_, id)| id == self).map(|(key, _)| *key)
+	}
+
+	pub fn from_key(key: &str) -> Option<TileId> {
+		TILE_KEYS.iter().find(|(name, _)| *name == key).map(|(_, id)| *id)
+	}
+
+	/// If the tile falls down when there is nothing below it.
+	pub fn has_gravity(&self) -> bool {
+		matches!(*self, SAND | SILT_BLOCK | EBONSAND_BLOCK | PEARLSAND_BLOCK)
+	}
+
+	/// What this tile needs around it to not break.
+	pub fn get_support_rule(&self) -> SupportRule {
+		match *self {
+			REGULAR_VINE | JUNGLE_VINE => SupportRule::SolidAbove,
+			JUNGLE_THORNY_BUSH => SupportRule::SolidBelow,
+			COBWEB | SPIKE => SupportRule::AnySide,
+			_ => SupportRule::None,
+		}
+	}
+
+	/// How much damage the tile takes before it breaks. Tools deal their power as damage on every hit.
+	pub fn get_hardness(&self) -> u32 {
+		match *self {
+			AIR => 0,
+			REGULAR_VINE | JUNGLE_VINE | COBWEB | JUNGLE_THORNY_BUSH => 1,
+			_ if ObjectType::get(self).is_some() => 1,
+			DIRT | GRASS | CORRUPT_GRASS | JUNGLE_GRASS | MUSHROOM_GRASS | HALLOWED_GRASS | SAND | EBONSAND_BLOCK | PEARLSAND_BLOCK
+			| SILT_BLOCK | MUD_BLOCK | CLAY_BLOCK | ASH_BLOCK | SNOW_BLOCK | PLATFORM | GLASS => 50,
+			EBONSTONE | HELLSTONE | BLUE_BRICK | GREEN_BRICK | PINK_BRICK | COBALT_ORE => 200,
+			MYTHRIL_ORE => 300,
+			ADAMANTITE_ORE | SPIKE => 400,
+			_ => 100,
+		}
+	}
+
+	/// The tool power needed to damage the tile at all.
+	pub fn get_required_power(&self) -> u32 {
+		match *self {
+			METEORITE => 50,
+			DEMONITE_ORE | OBSIDIAN => 55,
+			EBONSTONE | HELLSTONE => 65,
+			COBALT_ORE => 100,
+			MYTHRIL_ORE => 110,
+			ADAMANTITE_ORE => 150,
+			_ => 0,
+		}
+	}
+
+	/// If players and rays get stopped by this tile.
+	pub fn get_collision_type(&self) -> CollisionType {
+		match *self {
+			AIR | REGULAR_VINE | JUNGLE_VINE | COBWEB | JUNGLE_THORNY_BUSH => CollisionType::Nothing,
+			DOOR => CollisionType::CollidesPlayer,
+			PLATFORM => CollisionType::Platform,
+			_ if ObjectType::get(self).is_some() => CollisionType::Nothing,
+			_ => CollisionType::CollidesPlayer,
+		}
+	}
+}
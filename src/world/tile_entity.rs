@@ -0,0 +1,158 @@
+use std::io::{Read, Result, Write};
+
+use crate::item::inventory::Inventory;
+use crate::misc::util::CallbackResponse;
+use crate::world::save::{invalid_data, Persistent};
+use crate::world::tick::Tickable;
+use crate::world::tile;
+use crate::world::tile::TileId;
+
+pub const CHEST_SLOTS: usize = 40;
+/// How long a furnace keeps burning after it got used.
+pub const FURNACE_BURN_TIME: u32 = 60 * 10;
+
+/// State attached to a single tile that does not fit into the `Copy` tile grid.
+#[derive(Clone)]
+pub enum TileEntity {
+	Chest(Chest),
+	Furnace(Furnace),
+	Sign(Sign),
+}
+
+impl TileEntity {
+	/// Creates the entity a freshly placed tile owns, if it owns one.
+	pub fn create(id: &TileId) -> Option<TileEntity> {
+		match *id {
+			tile::CHEST => Some(TileEntity::Chest(Chest { inventory: Inventory::new(CHEST_SLOTS) })),
+			tile::FURNACE => Some(TileEntity::Furnace(Furnace { burn_time: 0 })),
+			tile::SIGN => Some(TileEntity::Sign(Sign { text: String::new() })),
+			_ => None,
+		}
+	}
+
+	/// If this entity belongs to a tile of type `id`.
+	pub fn is_owned_by(&self, id: &TileId) -> bool {
+		match self {
+			TileEntity::Chest(_) => *id == tile::CHEST,
+			TileEntity::Furnace(_) => *id == tile::FURNACE,
+			TileEntity::Sign(_) => *id == tile::SIGN,
+		}
+	}
+
+	/// Only entities that return true here get ticked.
+	pub fn needs_tick(&self) -> bool {
+		match self {
+			TileEntity::Furnace(furnace) => furnace.burn_time > 0,
+			TileEntity::Chest(_) | TileEntity::Sign(_) => false,
+		}
+	}
+
+}
+
+impl Tickable for TileEntity {
+	fn tick(&mut self) -> CallbackResponse {
+		match self {
+			TileEntity::Furnace(furnace) => {
+				furnace.burn_time = furnace.burn_time.saturating_sub(1);
+				if furnace.burn_time > 0 { CallbackResponse::Continue } else { CallbackResponse::Stop }
+			}
+			TileEntity::Chest(_) | TileEntity::Sign(_) => CallbackResponse::Stop,
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct Chest {
+	pub inventory: Inventory,
+}
+
+#[derive(Clone)]
+pub struct Furnace {
+	/// Ticks left until the fire goes out.
+	pub burn_time: u32,
+}
+
+impl Furnace {
+	pub fn ignite(&mut self) {
+		self.burn_time = FURNACE_BURN_TIME;
+	}
+
+	pub fn is_burning(&self) -> bool {
+		self.burn_time > 0
+	}
+}
+
+#[derive(Clone)]
+pub struct Sign {
+	pub text: String,
+}
+
+impl Persistent for TileEntity {
+	fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+		match self {
+			TileEntity::Chest(chest) => {
+				0u8.write(out)?;
+				chest.inventory.write(out)
+			}
+			TileEntity::Furnace(furnace) => {
+				1u8.write(out)?;
+				furnace.burn_time.write(out)
+			}
+			TileEntity::Sign(sign) => {
+				2u8.write(out)?;
+				sign.text.write(out)
+			}
+		}
+	}
+
+	fn read<R: Read>(input: &mut R) -> Result<Self> {
+		match u8::read(input)? {
+			0 => Ok(TileEntity::Chest(Chest { inventory: Inventory::read(input)? })),
+			1 => Ok(TileEntity::Furnace(Furnace { burn_time: u32::read(input)? })),
+			2 => Ok(TileEntity::Sign(Sign { text: String::read(input)? })),
+			kind => Err(invalid_data(format!("Unknown tile entity {}", kind))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::item::{ItemId, IRON_BAR};
+	use crate::item::stack::ItemStack;
+	use crate::misc::pos::{ChunkPos, WorldPos};
+	use crate::world::testing::{create_chunk, create_world};
+	use crate::world::tile;
+	use crate::world::tile::Tile;
+	use crate::world::tile_entity::TileEntity;
+
+	#[test]
+	fn broken_chest_drops_its_contents() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, y| if y == 0 { tile::STONE } else { tile::AIR }))]);
+		let origin = WorldPos::new(3, 1);
+		world.place_object(&origin, tile::CHEST).unwrap();
+		match world.get_tile_entity_mut(&origin) {
+			Some(TileEntity::Chest(chest)) => assert!(chest.inventory.insert(ItemStack::new(IRON_BAR, 5)).is_none()),
+			_ => panic!("Chest has no entity"),
+		}
+
+		// Breaking any part breaks the whole chest.
+		assert!(world.break_tile(&WorldPos::new(4, 2)));
+		assert!(world.get_tile_entity(&origin).is_none());
+		let drops: Vec<ItemStack> = world.get_drops().iter().map(|drop| drop.stack).collect();
+		assert!(drops.contains(&ItemStack::new(IRON_BAR, 5)));
+		assert!(drops.contains(&ItemStack::new(ItemId::Block(tile::CHEST), 1)));
+	}
+
+	#[test]
+	fn replaced_chest_drops_its_contents() {
+		let mut world = create_world(vec![(ChunkPos::new(0, 0), create_chunk(|_, y| if y == 0 { tile::STONE } else { tile::AIR }))]);
+		let origin = WorldPos::new(3, 1);
+		world.place_object(&origin, tile::CHEST).unwrap();
+		if let Some(TileEntity::Chest(chest)) = world.get_tile_entity_mut(&origin) {
+			chest.inventory.insert(ItemStack::new(IRON_BAR, 2));
+		}
+
+		world.set(&origin, Tile::id(tile::STONE));
+		assert!(world.get_drops().iter().any(|drop| drop.stack == ItemStack::new(IRON_BAR, 2)));
+	}
+}
@@ -1,10 +1,14 @@
+use crate::misc::util::Direction;
 use crate::world::neighbor::{NeighborAware, NeighborMatrix, NeighborType};
+use crate::world::paint;
+use crate::world::paint::PaintId;
 use crate::world::wall;
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Wall {
 	pub id: WallId,
 	pub variance: u8,
+	pub paint: PaintId,
 	matrix: NeighborMatrix,
 }
 
@@ -13,6 +17,7 @@ impl Wall {
 		Self {
 			variance: 0,
 			id: WallId { id: 0 },
+			paint: paint::NONE,
 			matrix: NeighborMatrix::default(),
 		}
 	}
@@ -21,6 +26,7 @@ impl Wall {
 		Self {
 			variance: 0,
 			id,
+			paint: paint::NONE,
 			matrix: NeighborMatrix::default(),
 		}
 	}
@@ -35,7 +41,11 @@ impl NeighborAware for Wall {
 		&self.matrix
 	}
 
-	fn apply_neighbor(&self, neighbor: &Wall) -> NeighborType {
+	fn get_neighbor_matrix_mut(&mut self) -> &mut NeighborMatrix {
+		&mut self.matrix
+	}
+
+	fn apply_neighbor(&self, neighbor: &Wall, _direction: Direction) -> NeighborType {
 		if neighbor.id == wall::AIR {
 			NeighborType::Air
 		} else /*if neighbor.id == target.id*/ {
@@ -47,6 +57,9 @@ impl NeighborAware for Wall {
 macro_rules! pub_const_wall_id {
     ($($NAME:ident $ID:expr;)*) => {
         $(pub const $NAME: WallId = WallId { id: $ID };)*
+
+        /// Every named wall id with the key it gets saved as in files that outlive id changes.
+        pub const WALL_KEYS: &[(&str, WallId)] = &[$((stringify!($NAME), $NAME),)*];
     };
 }
 
@@ -69,3 +82,14 @@ pub_const_wall_id! {
 pub struct WallId {
 	pub id: u32,
 }
+
+impl WallId {
+	/// The key of this id. Ids that share a number get the key of the first one.
+	pub fn get_key(&self) -> Option<&'static str> {
+		WALL_KEYS.iter().find(|(_, id)| id == self).map(|(key, _)| *key)
+	}
+
+	pub fn from_key(key: &str) -> Option<WallId> {
+		WALL_KEYS.iter().find(|(name, _)| *name == key).map(|(_, id)| *id)
+	}
+}